@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use criterion::Throughput;
+use wolfpack::archive::ArchiveWrite;
+use wolfpack::archive::CpioBuilder;
+use wolfpack::archive::TarBuilder;
+
+const NUM_FILES: usize = 10_000;
+const FILE_LEN: usize = 1024;
+
+fn archive_writing(c: &mut Criterion) {
+    let files: Vec<(PathBuf, Vec<u8>)> = (0..NUM_FILES)
+        .map(|i| {
+            let path = PathBuf::from(format!("usr/share/wolfpack/{}/file-{}", i % 100, i));
+            let contents = vec![(i % 256) as u8; FILE_LEN];
+            (path, contents)
+        })
+        .collect();
+    let mut group = c.benchmark_group("archive");
+    group.throughput(Throughput::Bytes((NUM_FILES * FILE_LEN) as u64));
+    group.bench_function("write cpio", |b| {
+        b.iter(|| CpioBuilder::from_files(files.iter().map(|(p, d)| (p, d)), Vec::new()).unwrap())
+    });
+    group.bench_function("write tar", |b| {
+        b.iter(|| TarBuilder::from_files(files.iter().map(|(p, d)| (p, d)), Vec::new()).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, archive_writing);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+use std::io::Cursor;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use criterion::Throughput;
+use tempfile::TempDir;
+use wolfpack::macos::Bom;
+
+const NUM_FILES: usize = 10_000;
+
+fn bom_round_trip(c: &mut Criterion) {
+    let workdir = TempDir::new().unwrap();
+    for i in 0..NUM_FILES {
+        let dir = workdir
+            .path()
+            .join(format!("usr/share/wolfpack/{}", i % 100));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(format!("file-{i}")), i.to_string()).unwrap();
+    }
+    let bom = Bom::from_directory(workdir.path()).unwrap();
+    let mut bytes = Cursor::new(Vec::new());
+    bom.write(&mut bytes).unwrap();
+    let bytes = bytes.into_inner();
+    let mut group = c.benchmark_group("bom");
+    group.throughput(Throughput::Elements(NUM_FILES as u64));
+    group.sample_size(10);
+    group.bench_function("from directory", |b| {
+        b.iter(|| Bom::from_directory(workdir.path()).unwrap())
+    });
+    group.bench_function("write", |b| {
+        b.iter(|| {
+            let mut writer = Cursor::new(Vec::with_capacity(bytes.len()));
+            bom.write(&mut writer).unwrap();
+            writer
+        })
+    });
+    group.bench_function("read", |b| b.iter(|| Bom::read(&bytes[..]).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, bom_round_trip);
+criterion_main!(benches);
@@ -0,0 +1,108 @@
+use std::fs::File;
+use std::path::Path;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BatchSize;
+use criterion::Criterion;
+use criterion::Throughput;
+use tempfile::TempDir;
+use walkdir::WalkDir;
+use wolfpack::deb::Package;
+use wolfpack::deb::PackageSigner;
+use wolfpack::deb::PackageVerifier;
+use wolfpack::deb::Repository;
+use wolfpack::deb::SigningKey;
+use wolfpack::sign::PgpCleartextSigner;
+
+const NUM_PACKAGES: usize = 100;
+const NUM_DESCRIPTION_LINES: usize = 100;
+
+fn control_parsing(c: &mut Criterion) {
+    let controls: Vec<String> = (0..NUM_PACKAGES).map(control).collect();
+    let len: usize = controls.iter().map(String::len).sum();
+    let mut group = c.benchmark_group("deb");
+    group.throughput(Throughput::Bytes(len as u64));
+    group.bench_function("parse control", |b| {
+        b.iter(|| {
+            for control in controls.iter() {
+                let _: Package = control.parse().unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+fn repository_generation(c: &mut Criterion) {
+    let (signing_key, verifying_key) = SigningKey::generate("wolfpack-pgp-id".into()).unwrap();
+    let signer = PackageSigner::new(signing_key.clone());
+    let verifier = PackageVerifier::new(verifying_key);
+    let release_signer = PgpCleartextSigner::new(signing_key.into());
+    let workdir = TempDir::new().unwrap();
+    let rootfs = workdir.path().join("rootfs");
+    let packages_dir = workdir.path().join("packages");
+    std::fs::create_dir_all(rootfs.join("usr/share/doc")).unwrap();
+    std::fs::create_dir_all(&packages_dir).unwrap();
+    std::fs::write(rootfs.join("usr/share/doc/README"), "hello world").unwrap();
+    for i in 0..NUM_PACKAGES {
+        let control: Package = control(i).parse().unwrap();
+        let path = packages_dir.join(format!("pkg-{i}_1.{i}.0_amd64.deb"));
+        control
+            .write(&rootfs, File::create(&path).unwrap(), &signer)
+            .unwrap();
+    }
+    let mut group = c.benchmark_group("deb");
+    group.throughput(Throughput::Elements(NUM_PACKAGES as u64));
+    group.sample_size(10);
+    group.bench_function("generate repository", |b| {
+        b.iter_batched(
+            || {
+                // `Repository::new` moves the packages to the output directory.
+                let output_dir = TempDir::new().unwrap();
+                let input_dir = output_dir.path().join("input");
+                copy_dir(&packages_dir, &input_dir);
+                (output_dir, input_dir)
+            },
+            |(output_dir, input_dir)| {
+                Repository::new(output_dir.path(), [input_dir], &verifier)
+                    .unwrap()
+                    .write(output_dir.path(), "meta".parse().unwrap(), &release_signer)
+                    .unwrap();
+                output_dir
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+fn control(i: usize) -> String {
+    let mut control = format!(
+        "Package: pkg-{i}
+Version: 1.{i}.0
+License: MIT
+Architecture: amd64
+Maintainer: Wolfpack <wolfpack@example.com>
+Installed-Size: 1024
+Depends: libc6 (>= 2.36), libfoo{i} | libbar, zlib1g
+Description: synthetic package {i}
+"
+    );
+    for j in 0..NUM_DESCRIPTION_LINES {
+        control.push_str(&format!(
+            " Line {j} of the long description of package {i}.\n"
+        ));
+    }
+    control
+}
+
+fn copy_dir(from: &Path, to: &Path) {
+    std::fs::create_dir_all(to).unwrap();
+    for entry in WalkDir::new(from).min_depth(1).into_iter() {
+        let entry = entry.unwrap();
+        std::fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+    }
+}
+
+criterion_group!(benches, control_parsing, repository_generation);
+criterion_main!(benches);
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use criterion::Throughput;
+use wolfpack::rpm::Entry;
+use wolfpack::rpm::HashAlgorithm;
+use wolfpack::rpm::Header;
+use wolfpack::rpm::Package;
+use wolfpack::rpm::Tag;
+
+const NUM_FILES: usize = 10_000;
+
+fn header_serialization(c: &mut Criterion) {
+    let header = header();
+    let bytes = header.to_vec().unwrap();
+    let mut group = c.benchmark_group("rpm");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("write header", |b| {
+        b.iter(|| header.to_vec().unwrap());
+    });
+    group.bench_function("read header", |b| {
+        b.iter(|| Header::<Entry>::read(&bytes[..]).unwrap());
+    });
+    group.finish();
+}
+
+fn header() -> Header<Entry> {
+    let package = Package {
+        name: "wolfpack".into(),
+        version: "1.0.0".into(),
+        summary: "synthetic package".into(),
+        description: "synthetic package with many files".into(),
+        license: "MIT".into(),
+        url: "https://example.com/".into(),
+        arch: "x86_64".into(),
+    };
+    let mut basenames = Vec::with_capacity(NUM_FILES);
+    let mut dirnames = Vec::with_capacity(NUM_FILES);
+    let mut dirindices = Vec::with_capacity(NUM_FILES);
+    let mut usernames = Vec::with_capacity(NUM_FILES);
+    let mut groupnames = Vec::with_capacity(NUM_FILES);
+    let mut filedigests = Vec::with_capacity(NUM_FILES);
+    let mut filemodes = Vec::with_capacity(NUM_FILES);
+    let mut filesizes = Vec::with_capacity(NUM_FILES);
+    for i in 0..NUM_FILES {
+        basenames.push(CString::new(format!("file-{i}")).unwrap());
+        dirnames.push(CString::new(format!("/usr/share/wolfpack/{}/", i % 100)).unwrap());
+        dirindices.push(i as u32);
+        usernames.push(c"root".into());
+        groupnames.push(c"root".into());
+        filedigests.push(CString::new(format!("{:064x}", i)).unwrap());
+        filemodes.push(0o100644);
+        filesizes.push(i as u32);
+    }
+    let mut entries: HashMap<Tag, Entry> = package.into();
+    entries.extend([
+        Entry::BaseNames(basenames.try_into().unwrap()).into(),
+        Entry::DirNames(dirnames.try_into().unwrap()).into(),
+        Entry::DirIndexes(dirindices.try_into().unwrap()).into(),
+        Entry::FileUserName(usernames.try_into().unwrap()).into(),
+        Entry::FileGroupName(groupnames.try_into().unwrap()).into(),
+        Entry::FileDigestAlgo(HashAlgorithm::Sha256).into(),
+        Entry::FileDigests(filedigests.try_into().unwrap()).into(),
+        Entry::FileModes(filemodes.try_into().unwrap()).into(),
+        Entry::FileSizes(filesizes.try_into().unwrap()).into(),
+    ]);
+    Header::new(entries)
+}
+
+criterion_group!(benches, header_serialization);
+criterion_main!(benches);
@@ -10,6 +10,7 @@ use normalize_path::NormalizePath;
 use crate::archive::ArchiveEntry;
 use crate::archive::ArchiveRead;
 use crate::archive::ArchiveWrite;
+use crate::archive::FileOptions;
 
 impl<W: Write> ArchiveWrite<W> for ar::Builder<W> {
     fn new(writer: W) -> Self {
@@ -34,6 +35,7 @@ impl<W: Write> ArchiveWrite<W> for ar::Builder<W> {
         &mut self,
         path: P,
         _metadata: &Metadata,
+        _options: &FileOptions,
         contents: C,
     ) -> Result<(), Error> {
         self.add_regular_file(path, contents)
@@ -10,15 +10,20 @@ use cpio::NewcBuilder as Entry;
 use normalize_path::NormalizePath;
 
 use crate::archive::ArchiveWrite;
+use crate::archive::FileOptions;
+use crate::archive::InodeAllocator;
 
 pub struct CpioBuilder<W: Write> {
     writer: W,
-    ino: u32,
+    inodes: InodeAllocator,
 }
 
 impl<W: Write> ArchiveWrite<W> for CpioBuilder<W> {
     fn new(writer: W) -> Self {
-        Self { writer, ino: 0 }
+        Self {
+            writer,
+            inodes: InodeAllocator::new(u32::MAX),
+        }
     }
 
     fn add_regular_file<P: AsRef<Path>, C: AsRef<[u8]>>(
@@ -35,18 +40,18 @@ impl<W: Write> ArchiveWrite<W> for CpioBuilder<W> {
                 path.display()
             )));
         }
-        eprintln!("cpio add {:?}", path.to_str().unwrap());
+        log::debug!("cpio add {:?}", path.to_str().unwrap());
+        let ino = self.inodes.allocate(&path)?;
         let mut entry_writer = Entry::new(
             path.to_str()
                 .ok_or_else(|| Error::other(format!("non utf-8 path: {}", path.display())))?,
         )
         .mode(0o644)
         .set_mode_file_type(ModeFileType::Regular)
-        .ino(self.ino)
+        .ino(ino)
         .write(&mut self.writer, contents.len() as u32);
         entry_writer.write_all(contents)?;
         let _ = entry_writer.finish();
-        self.ino += 1;
         Ok(())
     }
 
@@ -54,38 +59,51 @@ impl<W: Write> ArchiveWrite<W> for CpioBuilder<W> {
         &mut self,
         path: P,
         meta: &Metadata,
+        options: &FileOptions,
         contents: C,
     ) -> Result<(), Error> {
-        let path = path.as_ref().normalize();
+        let mode = options.mode.unwrap_or_else(|| meta.mode());
+        self.append(path.as_ref(), meta, mode, contents.as_ref())
+    }
+
+    fn into_inner(self) -> Result<W, Error> {
+        trailer(self.writer)
+    }
+}
+
+impl<W: Write> CpioBuilder<W> {
+    fn append(
+        &mut self,
+        path: &Path,
+        meta: &Metadata,
+        mode: u32,
+        contents: &[u8],
+    ) -> Result<(), Error> {
+        let path = path.normalize();
         let path = Path::new("/tmp/rpm").join(path);
-        let contents = contents.as_ref();
         if contents.len() > u32::MAX as usize {
             return Err(Error::other(format!(
                 "file is too large: {}",
                 path.display()
             )));
         }
-        eprintln!("cpio add {:?}", path.to_str().unwrap());
+        log::debug!("cpio add {:?}", path.to_str().unwrap());
+        let ino = self.inodes.allocate(&path)?;
         let mut entry_writer = Entry::new(
             path.to_str()
                 .ok_or_else(|| Error::other(format!("non utf-8 path: {}", path.display())))?,
         )
-        .mode(meta.mode())
+        .mode(mode)
         .set_mode_file_type(metadata_to_file_type(meta)?)
         .uid(meta.uid())
         .gid(meta.gid())
         .mtime(meta.mtime() as u32)
-        .ino(self.ino)
+        .ino(ino)
         .write(&mut self.writer, contents.len() as u32);
         entry_writer.write_all(contents)?;
         let _ = entry_writer.finish();
-        self.ino += 1;
         Ok(())
     }
-
-    fn into_inner(self) -> Result<W, Error> {
-        trailer(self.writer)
-    }
 }
 
 fn metadata_to_file_type(metadata: &Metadata) -> Result<ModeFileType, Error> {
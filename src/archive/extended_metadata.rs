@@ -0,0 +1,369 @@
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::fs::Metadata;
+use std::io::Error;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// File metadata that is not covered by [`Metadata`].
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedMetadata {
+    /// Extended attributes, e.g. `security.capability` or `com.apple.FinderInfo`.
+    pub xattrs: Vec<(OsString, Vec<u8>)>,
+    /// BSD file flags (`st_flags`).
+    pub flags: u32,
+}
+
+impl ExtendedMetadata {
+    /// Read extended attributes and file flags of the staged file.
+    ///
+    /// Attributes that only make sense on the build machine (quarantine flags, SELinux labels
+    /// etc.) are skipped.
+    pub fn read<P: AsRef<Path>>(path: P, metadata: &Metadata) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut xattrs = Vec::new();
+        let names = match xattr::list(path) {
+            Ok(names) => names,
+            Err(e) => {
+                log::debug!(
+                    "failed to list extended attributes of {}: {}",
+                    path.display(),
+                    e
+                );
+                return Ok(Self {
+                    xattrs,
+                    flags: file_flags(metadata),
+                });
+            }
+        };
+        for name in names {
+            if !is_portable_xattr(name.as_bytes()) {
+                continue;
+            }
+            match xattr::get(path, &name) {
+                Ok(Some(value)) => xattrs.push((name, value)),
+                Ok(None) => {}
+                Err(e) => log::debug!(
+                    "failed to read extended attribute {:?} of {}: {}",
+                    name,
+                    path.display(),
+                    e
+                ),
+            }
+        }
+        xattrs.sort();
+        Ok(Self {
+            xattrs,
+            flags: file_flags(metadata),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.xattrs.is_empty() && self.flags == 0
+    }
+
+    /// Convert to PAX extended header records understood by `libarchive`.
+    pub fn to_pax_extensions(&self) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        let mut extensions = Vec::with_capacity(self.xattrs.len() + 1);
+        for (name, value) in self.xattrs.iter() {
+            let name = name.to_str().ok_or_else(|| {
+                Error::other(format!("non utf-8 extended attribute name: {:?}", name))
+            })?;
+            extensions.push((format!("SCHILY.xattr.{}", name), value.clone()));
+        }
+        if self.flags != 0 {
+            extensions.push(("SCHILY.fflags".into(), flags_to_string(self.flags).into()));
+        }
+        Ok(extensions)
+    }
+
+    /// Convert extended attributes to AppleDouble file (`._<name>`) as written by macOS `ditto`.
+    ///
+    /// `com.apple.FinderInfo` and `com.apple.ResourceFork` are stored in the dedicated entries,
+    /// the other attributes are stored in the `ATTR` block of the Finder info entry. File flags
+    /// are not stored. Returns `None` if there are no extended attributes.
+    pub fn to_apple_double(&self) -> Option<Vec<u8>> {
+        if self.xattrs.is_empty() {
+            return None;
+        }
+        let mut finder_info = [0_u8; FINDER_INFO_LEN];
+        let mut resource_fork: &[u8] = &[];
+        let mut attrs = Vec::new();
+        for (name, value) in self.xattrs.iter() {
+            match name.as_bytes() {
+                FINDER_INFO if value.len() == FINDER_INFO_LEN => finder_info.copy_from_slice(value),
+                RESOURCE_FORK => resource_fork = value,
+                name if name.len() < u8::MAX as usize && !name.contains(&0) => {
+                    attrs.push((name, value))
+                }
+                _ => log::debug!("not storing extended attribute {:?} in AppleDouble", name),
+            }
+        }
+        let entries_len: usize = attrs
+            .iter()
+            .map(|(name, _)| align4(ATTR_ENTRY_LEN + name.len() + 1))
+            .sum();
+        let data_start = ATTR_HEADER_OFFSET + ATTR_HEADER_LEN + entries_len;
+        let data_len: usize = attrs.iter().map(|(_, value)| value.len()).sum();
+        let total_size = data_start + data_len;
+        let mut buf = Vec::with_capacity(total_size + resource_fork.len());
+        buf.extend(APPLE_DOUBLE_MAGIC.to_be_bytes());
+        buf.extend(APPLE_DOUBLE_VERSION.to_be_bytes());
+        buf.extend(APPLE_DOUBLE_FILLER);
+        buf.extend(2_u16.to_be_bytes());
+        for (id, offset, len) in [
+            (
+                FINDER_INFO_ID,
+                FINDER_INFO_OFFSET,
+                total_size - FINDER_INFO_OFFSET,
+            ),
+            (RESOURCE_FORK_ID, total_size, resource_fork.len()),
+        ] {
+            buf.extend(id.to_be_bytes());
+            buf.extend((offset as u32).to_be_bytes());
+            buf.extend((len as u32).to_be_bytes());
+        }
+        buf.extend(finder_info);
+        buf.extend([0_u8; 2]);
+        buf.extend(ATTR_MAGIC.to_be_bytes());
+        buf.extend(0_u32.to_be_bytes());
+        buf.extend((total_size as u32).to_be_bytes());
+        buf.extend((data_start as u32).to_be_bytes());
+        buf.extend((data_len as u32).to_be_bytes());
+        buf.extend([0_u8; 12]);
+        buf.extend(0_u16.to_be_bytes());
+        buf.extend((attrs.len() as u16).to_be_bytes());
+        let mut offset = data_start;
+        for (name, value) in attrs.iter() {
+            let start = buf.len();
+            buf.extend((offset as u32).to_be_bytes());
+            buf.extend((value.len() as u32).to_be_bytes());
+            buf.extend(0_u16.to_be_bytes());
+            buf.push((name.len() + 1) as u8);
+            buf.extend(*name);
+            buf.push(0);
+            buf.resize(start + align4(buf.len() - start), 0);
+            offset += value.len();
+        }
+        for (_, value) in attrs.iter() {
+            buf.extend(value.iter());
+        }
+        buf.extend(resource_fork);
+        Some(buf)
+    }
+
+    /// Read extended attributes from AppleDouble file.
+    pub fn from_apple_double(data: &[u8]) -> Result<Self, Error> {
+        let invalid = || Error::other("invalid AppleDouble file");
+        let u16_at = |offset: usize| -> Result<u16, Error> {
+            let bytes = data.get(offset..offset + 2).ok_or_else(invalid)?;
+            Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+        };
+        let u32_at = |offset: usize| -> Result<u32, Error> {
+            let bytes = data.get(offset..offset + 4).ok_or_else(invalid)?;
+            Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        };
+        let slice_at = |offset: usize, len: usize| -> Result<&[u8], Error> {
+            data.get(offset..offset.checked_add(len).ok_or_else(invalid)?)
+                .ok_or_else(invalid)
+        };
+        if u32_at(0)? != APPLE_DOUBLE_MAGIC || u32_at(4)? != APPLE_DOUBLE_VERSION {
+            return Err(invalid());
+        }
+        let mut xattrs = Vec::new();
+        let num_entries = u16_at(24)? as usize;
+        for i in 0..num_entries {
+            let entry = APPLE_DOUBLE_HEADER_LEN + i * APPLE_DOUBLE_ENTRY_LEN;
+            let id = u32_at(entry)?;
+            let offset = u32_at(entry + 4)? as usize;
+            let len = u32_at(entry + 8)? as usize;
+            let contents = slice_at(offset, len)?;
+            match id {
+                RESOURCE_FORK_ID if len != 0 => {
+                    xattrs.push((OsStr::from_bytes(RESOURCE_FORK).into(), contents.to_vec()));
+                }
+                FINDER_INFO_ID if len >= FINDER_INFO_LEN => {
+                    let finder_info = &contents[..FINDER_INFO_LEN];
+                    if finder_info.iter().any(|x| *x != 0) {
+                        xattrs.push((OsStr::from_bytes(FINDER_INFO).into(), finder_info.to_vec()));
+                    }
+                    let attr_header = offset + ATTR_HEADER_OFFSET - FINDER_INFO_OFFSET;
+                    if len < ATTR_HEADER_OFFSET - FINDER_INFO_OFFSET + ATTR_HEADER_LEN
+                        || u32_at(attr_header)? != ATTR_MAGIC
+                    {
+                        continue;
+                    }
+                    let num_attrs = u16_at(attr_header + ATTR_HEADER_LEN - 2)? as usize;
+                    let mut attr_entry = attr_header + ATTR_HEADER_LEN;
+                    for _ in 0..num_attrs {
+                        let value_offset = u32_at(attr_entry)? as usize;
+                        let value_len = u32_at(attr_entry + 4)? as usize;
+                        let name_len =
+                            *slice_at(attr_entry + 10, 1)?.first().ok_or_else(invalid)?;
+                        let name = slice_at(attr_entry + ATTR_ENTRY_LEN, name_len as usize)?;
+                        let name = name.strip_suffix(&[0]).unwrap_or(name);
+                        let value = slice_at(value_offset, value_len)?;
+                        xattrs.push((OsStr::from_bytes(name).into(), value.to_vec()));
+                        attr_entry += align4(ATTR_ENTRY_LEN + name_len as usize);
+                    }
+                }
+                _ => {}
+            }
+        }
+        xattrs.sort();
+        Ok(Self { xattrs, flags: 0 })
+    }
+}
+
+const fn align4(n: usize) -> usize {
+    (n + 3) & !3
+}
+
+fn is_portable_xattr(name: &[u8]) -> bool {
+    if NON_PORTABLE_XATTRS.contains(&name) {
+        return false;
+    }
+    name.starts_with(b"user.") || name.starts_with(b"com.apple.") || name == b"security.capability"
+}
+
+/// Convert file flags to comma-separated list of names as used by `chflags`.
+fn flags_to_string(flags: u32) -> String {
+    let mut names = Vec::new();
+    let mut unknown = flags;
+    for (flag, name) in FILE_FLAGS.iter() {
+        if flags & flag != 0 {
+            names.push(*name);
+            unknown &= !flag;
+        }
+    }
+    if unknown != 0 {
+        log::debug!("ignoring unknown file flags {:#x}", unknown);
+    }
+    names.join(",")
+}
+
+fn file_flags(metadata: &Metadata) -> u32 {
+    #[cfg(target_os = "freebsd")]
+    {
+        use std::os::freebsd::fs::MetadataExt;
+        metadata.st_flags()
+    }
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::macos::fs::MetadataExt;
+        metadata.st_flags()
+    }
+    #[cfg(not(any(target_os = "freebsd", target_os = "macos")))]
+    {
+        let _ = metadata;
+        0
+    }
+}
+
+const APPLE_DOUBLE_MAGIC: u32 = 0x00051607;
+const APPLE_DOUBLE_VERSION: u32 = 0x00020000;
+const APPLE_DOUBLE_FILLER: &[u8; 16] = b"Mac OS X        ";
+const APPLE_DOUBLE_HEADER_LEN: usize = 26;
+const APPLE_DOUBLE_ENTRY_LEN: usize = 12;
+const RESOURCE_FORK_ID: u32 = 2;
+const FINDER_INFO_ID: u32 = 9;
+/// Finder info follows the header and two entries.
+const FINDER_INFO_OFFSET: usize = APPLE_DOUBLE_HEADER_LEN + 2 * APPLE_DOUBLE_ENTRY_LEN;
+const FINDER_INFO_LEN: usize = 32;
+/// Finder info is followed by two bytes of padding and the header of the extended attributes.
+const ATTR_HEADER_OFFSET: usize = FINDER_INFO_OFFSET + FINDER_INFO_LEN + 2;
+const ATTR_HEADER_LEN: usize = 36;
+/// Offset, length, flags and name length.
+const ATTR_ENTRY_LEN: usize = 11;
+const ATTR_MAGIC: u32 = 0x41545452;
+const FINDER_INFO: &[u8] = b"com.apple.FinderInfo";
+const RESOURCE_FORK: &[u8] = b"com.apple.ResourceFork";
+
+const NON_PORTABLE_XATTRS: [&[u8]; 4] = [
+    b"com.apple.quarantine",
+    b"com.apple.provenance",
+    b"com.apple.macl",
+    b"com.apple.lastuseddate#PS",
+];
+
+const FILE_FLAGS: [(u32, &str); 10] = [
+    (0x00000001, "nodump"),
+    (0x00000002, "uchg"),
+    (0x00000004, "uappnd"),
+    (0x00000008, "opaque"),
+    (0x00000010, "uunlnk"),
+    (0x00008000, "hidden"),
+    (0x00010000, "arch"),
+    (0x00020000, "schg"),
+    (0x00040000, "sappnd"),
+    (0x00100000, "sunlnk"),
+];
+
+#[cfg(test)]
+mod tests {
+    use arbtest::arbtest;
+
+    use super::*;
+
+    #[test]
+    fn pax_extensions() {
+        let metadata = ExtendedMetadata {
+            xattrs: vec![("user.comment".into(), b"hello".to_vec())],
+            flags: 0x2 | 0x20000 | 0x80000000,
+        };
+        assert_eq!(
+            vec![
+                ("SCHILY.xattr.user.comment".to_string(), b"hello".to_vec()),
+                ("SCHILY.fflags".to_string(), b"uchg,schg".to_vec()),
+            ],
+            metadata.to_pax_extensions().unwrap()
+        );
+        assert!(ExtendedMetadata::default()
+            .to_pax_extensions()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn apple_double() {
+        let metadata = ExtendedMetadata {
+            xattrs: vec![
+                ("com.apple.FinderInfo".into(), [1_u8; 32].to_vec()),
+                ("com.apple.ResourceFork".into(), b"resource".to_vec()),
+                ("user.a".into(), b"hello".to_vec()),
+                ("user.comment".into(), b"".to_vec()),
+            ],
+            flags: 0,
+        };
+        let apple_double = metadata.to_apple_double().unwrap();
+        assert_eq!(&APPLE_DOUBLE_MAGIC.to_be_bytes(), &apple_double[..4]);
+        assert_eq!(b"ATTR", &apple_double[ATTR_HEADER_OFFSET..][..4]);
+        assert!(apple_double.ends_with(b"resource"));
+        assert_eq!(
+            metadata,
+            ExtendedMetadata::from_apple_double(&apple_double).unwrap()
+        );
+        assert!(ExtendedMetadata::default().to_apple_double().is_none());
+        assert!(ExtendedMetadata::from_apple_double(b"").is_err());
+        assert!(ExtendedMetadata::from_apple_double(&apple_double[..100]).is_err());
+    }
+
+    #[test]
+    fn apple_double_does_not_panic() {
+        arbtest(|u| {
+            let data: Vec<u8> = u.arbitrary()?;
+            let _ = ExtendedMetadata::from_apple_double(&data);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn portable_xattrs() {
+        assert!(is_portable_xattr(b"user.comment"));
+        assert!(is_portable_xattr(b"security.capability"));
+        assert!(is_portable_xattr(b"com.apple.FinderInfo"));
+        assert!(!is_portable_xattr(b"com.apple.quarantine"));
+        assert!(!is_portable_xattr(b"security.selinux"));
+        assert!(!is_portable_xattr(b"trusted.overlay.opaque"));
+    }
+}
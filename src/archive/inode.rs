@@ -0,0 +1,103 @@
+use std::collections::HashSet;
+use std::io::Error;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// Assigns inode numbers derived from the file paths.
+///
+/// Together with sorted directory traversal this makes the archives reproducible: the same
+/// directory always produces the same inode numbers no matter how the file system orders its
+/// entries.
+pub(crate) struct InodeAllocator {
+    used: HashSet<u32>,
+    max: u32,
+}
+
+impl InodeAllocator {
+    /// Allocate inode numbers in `1..=max` range.
+    pub(crate) fn new(max: u32) -> Self {
+        Self {
+            used: Default::default(),
+            max,
+        }
+    }
+
+    /// Fails when all `max` inode numbers are taken.
+    pub(crate) fn allocate(&mut self, path: &Path) -> Result<u32, Error> {
+        if self.used.len() >= self.max as usize {
+            return Err(Error::other(format!(
+                "too many files: inode numbers are limited to {}",
+                self.max
+            )));
+        }
+        let mut inode = fnv1a(path.as_os_str().as_bytes()) % self.max + 1;
+        // Resolve collisions by linear probing.
+        while !self.used.insert(inode) {
+            inode = inode % self.max + 1;
+        }
+        Ok(inode)
+    }
+}
+
+/// 32-bit FNV-1a hash.
+///
+/// Unlike `DefaultHasher` the result is stable across Rust versions and platforms.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    const OFFSET_BASIS: u32 = 0x811c9dc5;
+    const PRIME: u32 = 0x01000193;
+    let mut hash = OFFSET_BASIS;
+    for byte in bytes.iter() {
+        hash ^= *byte as u32;
+        hash = hash.wrapping_mul(PRIME);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use arbtest::arbtest;
+
+    use super::*;
+
+    #[test]
+    fn fnv1a_reference_values() {
+        assert_eq!(0x811c9dc5, fnv1a(b""));
+        assert_eq!(0xe40c292c, fnv1a(b"a"));
+        assert_eq!(0xbf9cf968, fnv1a(b"foobar"));
+    }
+
+    #[test]
+    fn inodes_are_unique_and_stable() {
+        arbtest(|u| {
+            let max: u32 = u.int_in_range(1..=100)?;
+            let paths: Vec<String> = u.arbitrary()?;
+            let paths: Vec<String> = paths
+                .into_iter()
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .take(max as usize)
+                .collect();
+            let mut allocator1 = InodeAllocator::new(max);
+            let mut allocator2 = InodeAllocator::new(max);
+            let mut inodes = HashSet::new();
+            for path in paths.iter() {
+                let inode = allocator1.allocate(Path::new(path)).unwrap();
+                assert!((1..=max).contains(&inode));
+                assert!(inodes.insert(inode));
+                assert_eq!(inode, allocator2.allocate(Path::new(path)).unwrap());
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn exhausted_inodes() {
+        let mut allocator = InodeAllocator::new(4);
+        let mut inodes = HashSet::new();
+        for path in ["a", "b", "c", "d"] {
+            assert!(inodes.insert(allocator.allocate(Path::new(path)).unwrap()));
+        }
+        assert_eq!(HashSet::from([1, 2, 3, 4]), inodes);
+        assert!(allocator.allocate(Path::new("e")).is_err());
+    }
+}
@@ -1,10 +1,18 @@
 mod ar;
 mod cpio;
+mod extended_metadata;
+mod inode;
+mod mode;
 mod read;
 mod tar;
+mod variant;
 mod write;
 
 pub use self::cpio::*;
+pub use self::extended_metadata::*;
+pub(crate) use self::inode::*;
+pub use self::mode::*;
 pub use self::read::*;
 pub use self::tar::*;
+pub use self::variant::*;
 pub use self::write::*;
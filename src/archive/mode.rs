@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+use std::fs::Metadata;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::path::PathBuf;
+
+use normalize_path::NormalizePath;
+
+/// File mode normalization policy.
+///
+/// Staged files often have arbitrary modes (e.g. `0777` or `0600`) that depend on the umask of
+/// the build machine. The policy replaces permission bits with the fixed ones:
+/// - directories get `0755`,
+/// - files with any executable bit get `0755`,
+/// - all other files get `0644`.
+///
+/// Setuid, setgid and sticky bits are never preserved unless the mode is explicitly set in
+/// `overrides`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModePolicy {
+    pub directory: u32,
+    pub executable: u32,
+    pub regular: u32,
+    /// Per-path modes. Paths are relative to the package root, leading `/` and `./` are ignored.
+    pub overrides: BTreeMap<PathBuf, u32>,
+}
+
+impl ModePolicy {
+    /// Set the mode of the specific file or directory.
+    pub fn with_override<P: AsRef<Path>>(mut self, path: P, mode: u32) -> Self {
+        self.overrides
+            .insert(normalize(path.as_ref()), mode & MODE_MASK);
+        self
+    }
+
+    /// Permission bits of the file that is located at `path` in the package.
+    pub fn mode(&self, path: &Path, metadata: &Metadata) -> u32 {
+        if let Some(mode) = self.overrides.get(&normalize(path)) {
+            return *mode;
+        }
+        if metadata.is_dir() {
+            self.directory
+        } else if metadata.mode() & 0o111 != 0 {
+            self.executable
+        } else {
+            self.regular
+        }
+    }
+}
+
+impl Default for ModePolicy {
+    fn default() -> Self {
+        Self {
+            directory: 0o755,
+            executable: 0o755,
+            regular: 0o644,
+            overrides: Default::default(),
+        }
+    }
+}
+
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let path = path.normalize();
+    match path.strip_prefix("/") {
+        Ok(path) => path.to_path_buf(),
+        Err(_) => path,
+    }
+}
+
+/// File type bits.
+pub(crate) const FILE_TYPE_MASK: u32 = 0o170000;
+const MODE_MASK: u32 = 0o7777;
+
+#[cfg(test)]
+mod tests {
+    use std::fs::create_dir;
+    use std::fs::set_permissions;
+    use std::fs::File;
+    use std::fs::Permissions;
+    use std::os::unix::fs::PermissionsExt;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn normalize_modes() {
+        let workdir = TempDir::new().unwrap();
+        let dir = workdir.path().join("dir");
+        create_dir(&dir).unwrap();
+        set_permissions(&dir, Permissions::from_mode(0o700)).unwrap();
+        for (name, mode) in [("private", 0o600), ("script", 0o700), ("suid", 0o4777)] {
+            let path = dir.join(name);
+            File::create(&path).unwrap();
+            set_permissions(&path, Permissions::from_mode(mode)).unwrap();
+        }
+        let mode = |policy: &ModePolicy, path: &str| {
+            let metadata = std::fs::metadata(workdir.path().join(path)).unwrap();
+            policy.mode(Path::new(path), &metadata)
+        };
+        let policy = ModePolicy::default();
+        assert_eq!(0o755, mode(&policy, "dir"));
+        assert_eq!(0o644, mode(&policy, "dir/private"));
+        assert_eq!(0o755, mode(&policy, "dir/script"));
+        assert_eq!(0o755, mode(&policy, "dir/suid"));
+        let policy = ModePolicy::default()
+            .with_override("/dir/suid", 0o4755)
+            .with_override("./dir", 0o700);
+        assert_eq!(0o4755, mode(&policy, "dir/suid"));
+        assert_eq!(0o700, mode(&policy, "dir"));
+        assert_eq!(0o644, mode(&policy, "dir/private"));
+    }
+}
@@ -10,6 +10,8 @@ use normalize_path::NormalizePath;
 use crate::archive::ArchiveEntry;
 use crate::archive::ArchiveRead;
 use crate::archive::ArchiveWrite;
+use crate::archive::ExtendedMetadata;
+use crate::archive::FileOptions;
 
 /// This builder retains original paths in the tar file.
 ///
@@ -20,6 +22,8 @@ pub struct TarBuilder<W: Write> {
 }
 
 impl<W: Write> ArchiveWrite<W> for TarBuilder<W> {
+    const EXTENDED_METADATA: bool = true;
+
     fn new(writer: W) -> Self {
         Self {
             inner: tar::Builder::new(writer),
@@ -55,16 +59,36 @@ impl<W: Write> ArchiveWrite<W> for TarBuilder<W> {
         &mut self,
         path: P,
         metadata: &Metadata,
+        options: &FileOptions,
         contents: C,
     ) -> Result<(), Error> {
-        let path = path.as_ref().normalize();
+        self.append_extended_metadata(&options.extended_metadata)?;
+        self.append(path.as_ref(), metadata, options.mode, contents.as_ref())
+    }
+
+    fn into_inner(self) -> Result<W, Error> {
+        self.inner.into_inner()
+    }
+}
+
+impl<W: Write> TarBuilder<W> {
+    fn append(
+        &mut self,
+        path: &Path,
+        metadata: &Metadata,
+        mode: Option<u32>,
+        contents: &[u8],
+    ) -> Result<(), Error> {
+        let path = path.normalize();
         let relative_path = match path.strip_prefix("/") {
             Ok(relative_path) => relative_path,
             Err(_) => &path,
         };
-        let contents = contents.as_ref();
         let mut header = tar::Header::new_ustar();
         header.set_metadata(metadata);
+        if let Some(mode) = mode {
+            header.set_mode(mode);
+        }
         header.set_size(contents.len() as u64);
         header.set_uid(0);
         header.set_gid(0);
@@ -76,8 +100,19 @@ impl<W: Write> ArchiveWrite<W> for TarBuilder<W> {
         Ok(())
     }
 
-    fn into_inner(self) -> Result<W, Error> {
-        self.inner.into_inner()
+    fn append_extended_metadata(
+        &mut self,
+        extended_metadata: &ExtendedMetadata,
+    ) -> Result<(), Error> {
+        if !extended_metadata.is_empty() {
+            let extensions = extended_metadata.to_pax_extensions()?;
+            self.inner.append_pax_extensions(
+                extensions
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_slice())),
+            )?;
+        }
+        Ok(())
     }
 }
 
@@ -110,46 +145,17 @@ impl<W: Write> ArchiveWrite<W> for tar::Builder<W> {
         path: P,
         contents: C,
     ) -> Result<(), Error> {
-        let contents = contents.as_ref();
-        let mut header = tar::Header::new_old();
-        header.set_size(contents.len() as u64);
-        header.set_uid(0);
-        header.set_gid(0);
-        header.set_mode(0o644);
-        header.set_entry_type(tar::EntryType::Regular);
-        header.set_path(path)?;
-        // TODO this has to be done for ipk only
-        let actual_path = &mut header.as_old_mut().name;
-        let n = actual_path.len();
-        actual_path.copy_within(..(n - 2), 2);
-        actual_path[0] = b'.';
-        actual_path[1] = b'/';
-        header.set_cksum();
-        self.append(&header, contents)?;
-        Ok(())
+        append_file(self, path, 0o644, contents)
     }
 
     fn add_regular_file_with_metadata<P: AsRef<Path>, C: AsRef<[u8]>>(
         &mut self,
         path: P,
         metadata: &Metadata,
+        options: &FileOptions,
         contents: C,
     ) -> Result<(), Error> {
-        let contents = contents.as_ref();
-        let mut header = tar::Header::new_old();
-        header.set_metadata(metadata);
-        header.set_size(contents.len() as u64);
-        header.set_uid(0);
-        header.set_gid(0);
-        header.set_path(path)?;
-        // TODO this has to be done for pkg only
-        let actual_path = &mut header.as_old_mut().name;
-        let n = actual_path.len();
-        actual_path.copy_within(..(n - 1), 1);
-        actual_path[0] = b'/';
-        header.set_cksum();
-        self.append(&header, contents)?;
-        Ok(())
+        append_file_with_metadata(self, path, metadata, options.mode, contents)
     }
 
     fn into_inner(self) -> Result<W, Error> {
@@ -157,6 +163,68 @@ impl<W: Write> ArchiveWrite<W> for tar::Builder<W> {
     }
 }
 
+/// Add regular file with `0755` mode, e.g. maintainer script.
+#[cfg(feature = "ipk")]
+pub(crate) fn add_executable_file<W: Write, P: AsRef<Path>, C: AsRef<[u8]>>(
+    builder: &mut tar::Builder<W>,
+    path: P,
+    contents: C,
+) -> Result<(), Error> {
+    append_file(builder, path, 0o755, contents)
+}
+
+fn append_file<W: Write, P: AsRef<Path>, C: AsRef<[u8]>>(
+    builder: &mut tar::Builder<W>,
+    path: P,
+    mode: u32,
+    contents: C,
+) -> Result<(), Error> {
+    let contents = contents.as_ref();
+    let mut header = tar::Header::new_old();
+    header.set_size(contents.len() as u64);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_mode(mode);
+    header.set_entry_type(tar::EntryType::Regular);
+    header.set_path(path)?;
+    // TODO this has to be done for ipk only
+    let actual_path = &mut header.as_old_mut().name;
+    let n = actual_path.len();
+    actual_path.copy_within(..(n - 2), 2);
+    actual_path[0] = b'.';
+    actual_path[1] = b'/';
+    header.set_cksum();
+    builder.append(&header, contents)?;
+    Ok(())
+}
+
+fn append_file_with_metadata<W: Write, P: AsRef<Path>, C: AsRef<[u8]>>(
+    builder: &mut tar::Builder<W>,
+    path: P,
+    metadata: &Metadata,
+    mode: Option<u32>,
+    contents: C,
+) -> Result<(), Error> {
+    let contents = contents.as_ref();
+    let mut header = tar::Header::new_old();
+    header.set_metadata(metadata);
+    if let Some(mode) = mode {
+        header.set_mode(mode);
+    }
+    header.set_size(contents.len() as u64);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_path(path)?;
+    // TODO this has to be done for pkg only
+    let actual_path = &mut header.as_old_mut().name;
+    let n = actual_path.len();
+    actual_path.copy_within(..(n - 1), 1);
+    actual_path[0] = b'/';
+    header.set_cksum();
+    builder.append(&header, contents)?;
+    Ok(())
+}
+
 impl<'a, R: 'a + Read> ArchiveRead<'a, R> for tar::Archive<R> {
     fn new(reader: R) -> Self {
         tar::Archive::<R>::new(reader)
@@ -180,3 +248,104 @@ impl<'a, R: Read> ArchiveEntry for tar::Entry<'a, R> {
         Ok(self.path()?.normalize())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn extended_metadata_write_read() {
+        let workdir = TempDir::new().unwrap();
+        let file = workdir.path().join("file");
+        std::fs::write(&file, "hello").unwrap();
+        let metadata = std::fs::metadata(&file).unwrap();
+        let options = FileOptions {
+            mode: None,
+            extended_metadata: ExtendedMetadata {
+                xattrs: vec![("user.comment".into(), b"hello".to_vec())],
+                flags: 0x2,
+            },
+        };
+        let mut builder = TarBuilder::new(Vec::new());
+        builder
+            .add_regular_file_with_metadata("/usr/share/file", &metadata, &options, "hello")
+            .unwrap();
+        builder
+            .add_regular_file_with_metadata(
+                "/usr/share/other",
+                &metadata,
+                &Default::default(),
+                "world",
+            )
+            .unwrap();
+        let bytes = builder.into_inner().unwrap();
+        let mut archive = tar::Archive::new(bytes.as_slice());
+        let mut extensions = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let mut pax: HashMap<String, Vec<u8>> = HashMap::new();
+            if let Some(pax_extensions) = entry.pax_extensions().unwrap() {
+                for extension in pax_extensions {
+                    let extension = extension.unwrap();
+                    pax.insert(
+                        extension.key().unwrap().to_string(),
+                        extension.value_bytes().to_vec(),
+                    );
+                }
+            }
+            extensions.push((entry.path().unwrap().into_owned(), pax));
+        }
+        assert_eq!(2, extensions.len());
+        assert_eq!(Path::new("/usr/share/file"), extensions[0].0);
+        assert_eq!(
+            Some(b"hello".as_slice()),
+            extensions[0]
+                .1
+                .get("SCHILY.xattr.user.comment")
+                .map(|x| x.as_slice())
+        );
+        assert_eq!(
+            Some(b"uchg".as_slice()),
+            extensions[0].1.get("SCHILY.fflags").map(|x| x.as_slice())
+        );
+        assert_eq!(Path::new("/usr/share/other"), extensions[1].0);
+        assert!(extensions[1].1.is_empty());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn xattrs_from_directory() {
+        let workdir = TempDir::new().unwrap();
+        let file = workdir.path().join("file");
+        std::fs::write(&file, "hello").unwrap();
+        xattr::set(&file, "user.comment", b"hello").unwrap();
+        let bytes = TarBuilder::from_directory(workdir.path(), Vec::new()).unwrap();
+        let mut archive = tar::Archive::new(bytes.as_slice());
+        let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+        let xattrs = entry
+            .pax_extensions()
+            .unwrap()
+            .unwrap()
+            .map(|extension| {
+                let extension = extension.unwrap();
+                (
+                    extension.key().unwrap().to_string(),
+                    extension.value_bytes().to_vec(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![("SCHILY.xattr.user.comment".to_string(), b"hello".to_vec())],
+            xattrs
+        );
+        // Old tar format does not store extended attributes.
+        let bytes = tar::Builder::from_directory(workdir.path(), Vec::new()).unwrap();
+        let mut archive = tar::Archive::new(bytes.as_slice());
+        let mut entry = archive.entries().unwrap().next().unwrap().unwrap();
+        assert!(entry.pax_extensions().unwrap().is_none());
+    }
+}
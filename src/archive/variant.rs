@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs::copy;
+use std::fs::create_dir_all;
+use std::fs::read_dir;
+use std::fs::read_link;
+use std::fs::set_permissions;
+use std::io::Error;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+use std::path::PathBuf;
+
+use normalize_path::NormalizePath;
+use walkdir::WalkDir;
+
+use crate::archive::normalize;
+
+/// Variant tags of the staged files.
+///
+/// Tags split one root file system into several packages, e.g. `foo`, `foo-doc` and `foo-dev`.
+/// Each tag applies to the path and everything under it, the longest matching path wins.
+/// Files without a tag are untagged.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Variants {
+    /// Paths are relative to the package root, leading `/` and `./` are ignored.
+    pub tags: BTreeMap<PathBuf, String>,
+}
+
+impl Variants {
+    /// Tag the file or the directory with `variant`.
+    pub fn with_tag<P: AsRef<Path>, S: Into<String>>(mut self, path: P, variant: S) -> Self {
+        self.tags.insert(normalize(path.as_ref()), variant.into());
+        self
+    }
+
+    /// The variant of the file that is located at `path` in the package.
+    pub fn variant(&self, path: &Path) -> Option<&str> {
+        let path = normalize(path);
+        path.ancestors()
+            .find_map(|ancestor| self.tags.get(ancestor))
+            .map(String::as_str)
+    }
+
+    /// Copy the files from `directory` that pass the `filter` to `output_dir`.
+    ///
+    /// The resulting directory can be passed to any package writer.
+    pub fn stage<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        directory: P1,
+        output_dir: P2,
+        filter: &VariantFilter,
+    ) -> Result<(), Error> {
+        let directory = directory.as_ref();
+        let output_dir = output_dir.as_ref();
+        create_dir_all(output_dir)?;
+        let mut entries = Vec::new();
+        // Directories are created only if they are empty or if any of their descendants is
+        // included.
+        let mut dirs = BTreeSet::new();
+        for entry in WalkDir::new(directory).sort_by_file_name().into_iter() {
+            let entry = entry?;
+            let entry_path = entry
+                .path()
+                .strip_prefix(directory)
+                .map_err(Error::other)?
+                .normalize();
+            if entry_path == Path::new("") {
+                continue;
+            }
+            if !filter.includes(self.variant(&entry_path)) {
+                continue;
+            }
+            let dir = if !entry.file_type().is_dir() {
+                entry_path.parent()
+            } else if read_dir(entry.path())?.next().is_none() {
+                Some(entry_path.as_path())
+            } else {
+                None
+            };
+            if let Some(dir) = dir {
+                dirs.extend(
+                    dir.ancestors()
+                        .filter(|ancestor| *ancestor != Path::new(""))
+                        .map(Path::to_path_buf),
+                );
+            }
+            entries.push((entry_path, entry));
+        }
+        for dir in dirs.iter() {
+            create_dir_all(output_dir.join(dir))?;
+        }
+        for (entry_path, entry) in entries.iter() {
+            let output_path = output_dir.join(entry_path);
+            let file_type = entry.file_type();
+            if file_type.is_dir() {
+                continue;
+            } else if file_type.is_symlink() {
+                symlink(read_link(entry.path())?, &output_path)?;
+            } else {
+                copy(entry.path(), &output_path)?;
+            }
+        }
+        // Permissions are copied last so that read-only directories can be populated.
+        for dir in dirs.iter().rev() {
+            let permissions = directory.join(dir).metadata()?.permissions();
+            set_permissions(output_dir.join(dir), permissions)?;
+        }
+        Ok(())
+    }
+}
+
+/// Variants that the package includes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VariantFilter {
+    /// Include the files without a variant.
+    pub untagged: bool,
+    pub variants: BTreeSet<String>,
+}
+
+impl VariantFilter {
+    /// Include only the files without a variant (the main package).
+    pub fn untagged() -> Self {
+        Self {
+            untagged: true,
+            variants: Default::default(),
+        }
+    }
+
+    /// Include only the files with the specified variants (e.g. `-doc` package).
+    pub fn only<I: IntoIterator<Item = S>, S: Into<String>>(variants: I) -> Self {
+        Self {
+            untagged: false,
+            variants: variants.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn includes(&self, variant: Option<&str>) -> bool {
+        match variant {
+            Some(variant) => self.variants.contains(variant),
+            None => self.untagged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::fs::Permissions;
+    use std::os::unix::fs::PermissionsExt;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn split_into_variants() {
+        let workdir = TempDir::new().unwrap();
+        let rootfs = workdir.path().join("rootfs");
+        for path in [
+            "usr/bin/foo",
+            "usr/include/foo.h",
+            "usr/lib/libfoo.so.1",
+            "usr/share/doc/foo/README",
+            "usr/share/doc/foo/examples/dev.c",
+        ] {
+            let path = rootfs.join(path);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            File::create(&path).unwrap();
+        }
+        symlink("libfoo.so.1", rootfs.join("usr/lib/libfoo.so")).unwrap();
+        set_permissions(rootfs.join("usr/share/doc"), Permissions::from_mode(0o750)).unwrap();
+        let variants = Variants::default()
+            .with_tag("/usr/share/doc", "doc")
+            .with_tag("./usr/include", "dev")
+            .with_tag("usr/lib/libfoo.so", "dev")
+            .with_tag("usr/share/doc/foo/examples", "dev");
+        assert_eq!(None, variants.variant(Path::new("usr/bin/foo")));
+        assert_eq!(
+            Some("doc"),
+            variants.variant(Path::new("usr/share/doc/foo/README"))
+        );
+        assert_eq!(
+            Some("dev"),
+            variants.variant(Path::new("/usr/share/doc/foo/examples/dev.c"))
+        );
+        let files = |filter: &VariantFilter| -> Vec<String> {
+            let output_dir = TempDir::new().unwrap();
+            variants.stage(&rootfs, output_dir.path(), filter).unwrap();
+            WalkDir::new(output_dir.path())
+                .min_depth(1)
+                .sort_by_file_name()
+                .into_iter()
+                .map(|entry| entry.unwrap())
+                .map(|entry| {
+                    let path = entry.path().strip_prefix(output_dir.path()).unwrap();
+                    if entry.file_type().is_dir() {
+                        let mode = entry.metadata().unwrap().permissions().mode() & 0o777;
+                        format!("{}/ {:o}", path.display(), mode)
+                    } else {
+                        path.display().to_string()
+                    }
+                })
+                .collect()
+        };
+        assert_eq!(
+            vec![
+                "usr/ 755",
+                "usr/bin/ 755",
+                "usr/bin/foo",
+                "usr/lib/ 755",
+                "usr/lib/libfoo.so.1"
+            ],
+            files(&VariantFilter::untagged())
+        );
+        assert_eq!(
+            vec![
+                "usr/ 755",
+                "usr/share/ 755",
+                "usr/share/doc/ 750",
+                "usr/share/doc/foo/ 755",
+                "usr/share/doc/foo/README"
+            ],
+            files(&VariantFilter::only(["doc"]))
+        );
+        assert_eq!(
+            vec![
+                "usr/ 755",
+                "usr/include/ 755",
+                "usr/include/foo.h",
+                "usr/lib/ 755",
+                "usr/lib/libfoo.so",
+                "usr/share/ 755",
+                "usr/share/doc/ 750",
+                "usr/share/doc/foo/ 755",
+                "usr/share/doc/foo/examples/ 755",
+                "usr/share/doc/foo/examples/dev.c"
+            ],
+            files(&VariantFilter::only(["dev"]))
+        );
+    }
+}
@@ -6,8 +6,16 @@ use std::path::Path;
 use normalize_path::NormalizePath;
 use walkdir::WalkDir;
 
+use crate::archive::ExtendedMetadata;
+use crate::archive::ModePolicy;
+
 // TODO generic Header class
 pub trait ArchiveWrite<W: Write> {
+    /// Whether the format stores extended attributes and file flags.
+    ///
+    /// Extended metadata is not read from the files for the formats that would ignore it.
+    const EXTENDED_METADATA: bool = false;
+
     fn new(writer: W) -> Self;
 
     fn add_regular_file<P: AsRef<Path>, C: AsRef<[u8]>>(
@@ -16,10 +24,15 @@ pub trait ArchiveWrite<W: Write> {
         contents: C,
     ) -> Result<(), Error>;
 
+    /// Add regular file with the metadata of the staged file.
+    ///
+    /// Formats that do not store file modes or extended metadata ignore the corresponding
+    /// options.
     fn add_regular_file_with_metadata<P: AsRef<Path>, C: AsRef<[u8]>>(
         &mut self,
         path: P,
         metadata: &Metadata,
+        options: &FileOptions,
         contents: C,
     ) -> Result<(), Error>;
 
@@ -40,6 +53,18 @@ pub trait ArchiveWrite<W: Write> {
     }
 
     fn from_directory<P>(directory: P, writer: W) -> Result<W, Error>
+    where
+        P: AsRef<Path>,
+        Self: Sized,
+    {
+        Self::from_directory_with_mode_policy(directory, &Default::default(), writer)
+    }
+
+    fn from_directory_with_mode_policy<P>(
+        directory: P,
+        mode_policy: &ModePolicy,
+        writer: W,
+    ) -> Result<W, Error>
     where
         P: AsRef<Path>,
         Self: Sized,
@@ -48,7 +73,7 @@ pub trait ArchiveWrite<W: Write> {
         // TODO hardlinks
         let directory = directory.as_ref();
         let mut archive = Self::new(writer);
-        for entry in WalkDir::new(directory).into_iter() {
+        for entry in WalkDir::new(directory).sort_by_file_name().into_iter() {
             let entry = entry?;
             let entry_path = entry
                 .path()
@@ -60,13 +85,31 @@ pub trait ArchiveWrite<W: Write> {
             }
             let relative_path = Path::new(".").join(entry_path);
             let metadata = std::fs::metadata(entry.path())?;
+            let extended_metadata = if Self::EXTENDED_METADATA {
+                ExtendedMetadata::read(entry.path(), &metadata)?
+            } else {
+                Default::default()
+            };
             let data = if entry.file_type().is_dir() {
                 Vec::new()
             } else {
                 std::fs::read(entry.path())?
             };
-            archive.add_regular_file_with_metadata(relative_path, &metadata, data)?;
+            let options = FileOptions {
+                mode: Some(mode_policy.mode(&relative_path, &metadata)),
+                extended_metadata,
+            };
+            archive.add_regular_file_with_metadata(relative_path, &metadata, &options, data)?;
         }
         archive.into_inner()
     }
 }
+
+/// Archive entry properties that are not taken from [`Metadata`] as is.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct FileOptions {
+    /// Permission bits that replace the ones from [`Metadata`].
+    pub mode: Option<u32>,
+    /// Extended attributes and file flags.
+    pub extended_metadata: ExtendedMetadata,
+}
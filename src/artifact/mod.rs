@@ -0,0 +1,3 @@
+mod name;
+
+pub use self::name::*;
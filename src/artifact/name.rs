@@ -0,0 +1,333 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::io::Error;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Package file name components.
+///
+/// | Format | File name |
+/// |--------|-----------|
+/// | `deb`, `ipk` | `<name>_<version>_<arch>.<ext>` |
+/// | `rpm` | `<name>-<version>-<release>.<arch>.rpm` |
+/// | FreeBSD `pkg`, macOS `pkg` | `<name>-<version>.pkg` |
+/// | `msix` | `<name>_<version>_<arch>.msix` or `<name>_<version>.msix` for neutral packages |
+///
+/// For `rpm` the version includes the release. For `deb` the version does not include the epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArtifactName {
+    pub name: String,
+    pub version: String,
+    pub arch: Option<String>,
+    pub format: ArtifactFormat,
+}
+
+impl ArtifactName {
+    /// Parse the file name of the package in the specified format.
+    pub fn parse(file_name: &str, format: ArtifactFormat) -> Result<Self, Error> {
+        Self::do_parse(file_name, format)
+            .ok_or_else(|| Error::other(format!("invalid {} file name: {:?}", format, file_name)))
+    }
+
+    /// Parse the file name of the package guessing the format from the extension.
+    ///
+    /// `.pkg` files are treated as FreeBSD packages.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .and_then(|x| x.to_str())
+            .ok_or_else(|| Error::other(format!("invalid file name: {}", path.display())))?;
+        file_name.parse()
+    }
+
+    /// Check that the file name of the package matches its metadata.
+    ///
+    /// Logs a warning if it does not.
+    pub fn check_path(&self, path: &Path) {
+        let actual = path.file_name().and_then(|x| x.to_str());
+        let expected = self.to_string();
+        if actual == Some(expected.as_str()) {
+            return;
+        }
+        match actual.map(|x| Self::parse(x, self.format)) {
+            Some(Ok(actual)) => log::warn!(
+                "misnamed package {}: expected {}, {}",
+                path.display(),
+                expected,
+                self.describe_mismatch(&actual)
+            ),
+            _ => log::warn!("misnamed package {}: expected {}", path.display(), expected),
+        }
+    }
+
+    fn describe_mismatch(&self, actual: &Self) -> String {
+        let mut mismatches = Vec::new();
+        if self.name != actual.name {
+            mismatches.push(format!("name {:?} != {:?}", actual.name, self.name));
+        }
+        if self.version != actual.version {
+            mismatches.push(format!(
+                "version {:?} != {:?}",
+                actual.version, self.version
+            ));
+        }
+        if self.arch != actual.arch {
+            mismatches.push(format!("arch {:?} != {:?}", actual.arch, self.arch));
+        }
+        mismatches.join(", ")
+    }
+
+    fn do_parse(file_name: &str, format: ArtifactFormat) -> Option<Self> {
+        let stem = file_name.strip_suffix(format.extension())?;
+        let (name, version, arch) = match format {
+            ArtifactFormat::Deb | ArtifactFormat::Ipk => {
+                let mut iter = stem.split('_');
+                let name = iter.next()?;
+                let version = iter.next()?;
+                let arch = iter.next()?;
+                if iter.next().is_some() {
+                    return None;
+                }
+                (name, version, Some(arch))
+            }
+            ArtifactFormat::Rpm => {
+                let (name_version_release, arch) = stem.rsplit_once('.')?;
+                let (name_version, release) = name_version_release.rsplit_once('-')?;
+                let (name, _) = name_version.rsplit_once('-')?;
+                if release.is_empty() {
+                    return None;
+                }
+                let version = &name_version_release[name.len() + 1..];
+                (name, version, Some(arch))
+            }
+            ArtifactFormat::Pkg | ArtifactFormat::MacosPkg => {
+                let (name, version) = stem.rsplit_once('-')?;
+                (name, version, None)
+            }
+            ArtifactFormat::Msix => {
+                let mut iter = stem.split('_');
+                let name = iter.next()?;
+                let version = iter.next()?;
+                let arch = iter.next();
+                if iter.next().is_some() {
+                    return None;
+                }
+                (name, version, arch)
+            }
+        };
+        if name.is_empty() || version.is_empty() || arch.is_some_and(|arch| arch.is_empty()) {
+            return None;
+        }
+        Some(Self {
+            name: name.into(),
+            version: version.into(),
+            arch: arch.map(Into::into),
+            format,
+        })
+    }
+}
+
+impl Display for ArtifactName {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let arch = self.arch.as_deref().unwrap_or(NO_ARCH);
+        match self.format {
+            ArtifactFormat::Deb | ArtifactFormat::Ipk => {
+                write!(f, "{}_{}_{}", self.name, self.version, arch)?
+            }
+            ArtifactFormat::Rpm => write!(f, "{}-{}.{}", self.name, self.version, arch)?,
+            ArtifactFormat::Pkg | ArtifactFormat::MacosPkg => {
+                write!(f, "{}-{}", self.name, self.version)?
+            }
+            ArtifactFormat::Msix => {
+                write!(f, "{}_{}", self.name, self.version)?;
+                if let Some(arch) = self.arch.as_ref() {
+                    write!(f, "_{}", arch)?;
+                }
+            }
+        }
+        f.write_str(self.format.extension())
+    }
+}
+
+impl FromStr for ArtifactName {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let format = ArtifactFormat::from_file_name(value)
+            .ok_or_else(|| Error::other(format!("unknown package format: {:?}", value)))?;
+        Self::parse(value, format)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(test, derive(arbitrary::Arbitrary))]
+pub enum ArtifactFormat {
+    Deb,
+    Ipk,
+    Rpm,
+    /// FreeBSD package.
+    Pkg,
+    /// macOS flat package.
+    MacosPkg,
+    Msix,
+}
+
+impl ArtifactFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Deb => ".deb",
+            Self::Ipk => ".ipk",
+            Self::Rpm => ".rpm",
+            Self::Pkg | Self::MacosPkg => ".pkg",
+            Self::Msix => ".msix",
+        }
+    }
+
+    /// Guess package format from the file name extension.
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        [Self::Deb, Self::Ipk, Self::Rpm, Self::Pkg, Self::Msix]
+            .into_iter()
+            .find(|format| file_name.ends_with(format.extension()))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Deb => "deb",
+            Self::Ipk => "ipk",
+            Self::Rpm => "rpm",
+            Self::Pkg => "pkg",
+            Self::MacosPkg => "macos-pkg",
+            Self::Msix => "msix",
+        }
+    }
+}
+
+impl Display for ArtifactFormat {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+const NO_ARCH: &str = "all";
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::Arbitrary;
+    use arbitrary::Unstructured;
+    use arbtest::arbtest;
+
+    use super::*;
+
+    #[test]
+    fn parse_file_names() {
+        assert_eq!(
+            ArtifactName {
+                name: "hello".into(),
+                version: "1.0-1".into(),
+                arch: Some("amd64".into()),
+                format: ArtifactFormat::Deb,
+            },
+            "hello_1.0-1_amd64.deb".parse().unwrap()
+        );
+        assert_eq!(
+            ArtifactName {
+                name: "hello-world".into(),
+                version: "1.0-1".into(),
+                arch: Some("x86_64".into()),
+                format: ArtifactFormat::Rpm,
+            },
+            "hello-world-1.0-1.x86_64.rpm".parse().unwrap()
+        );
+        assert_eq!(
+            ArtifactName {
+                name: "hello-world".into(),
+                version: "1.0_1,1".into(),
+                arch: None,
+                format: ArtifactFormat::Pkg,
+            },
+            "hello-world-1.0_1,1.pkg".parse().unwrap()
+        );
+        assert_eq!(
+            ArtifactName {
+                name: "Hello.World".into(),
+                version: "1.0.0.0".into(),
+                arch: None,
+                format: ArtifactFormat::Msix,
+            },
+            "Hello.World_1.0.0.0.msix".parse().unwrap()
+        );
+        assert_eq!(
+            ArtifactName {
+                name: "Hello.World".into(),
+                version: "1.0.0.0".into(),
+                arch: Some("x64".into()),
+                format: ArtifactFormat::Msix,
+            },
+            "Hello.World_1.0.0.0_x64.msix".parse().unwrap()
+        );
+        assert!("Hello.World_1.0.0.0_x64_extra.msix"
+            .parse::<ArtifactName>()
+            .is_err());
+        assert!("hello_1.0.deb".parse::<ArtifactName>().is_err());
+        assert!("hello_1.0_amd64_extra.ipk".parse::<ArtifactName>().is_err());
+        assert!("hello-1.0.x86_64.rpm".parse::<ArtifactName>().is_err());
+        assert!("hello.pkg".parse::<ArtifactName>().is_err());
+        assert!("hello_1.0.tar.gz".parse::<ArtifactName>().is_err());
+    }
+
+    #[test]
+    fn display_parse() {
+        arbtest(|u| {
+            let expected: ArtifactName = u.arbitrary()?;
+            let file_name = expected.to_string();
+            let actual = ArtifactName::parse(&file_name, expected.format).unwrap();
+            assert_eq!(expected, actual);
+            Ok(())
+        });
+    }
+
+    impl<'a> Arbitrary<'a> for ArtifactName {
+        fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+            let format: ArtifactFormat = u.arbitrary()?;
+            let name = arbitrary_word(u, "abcdefghijklmnopqrstuvwxyz0123456789.-+")?;
+            let version = match format {
+                ArtifactFormat::Rpm => format!(
+                    "{}-{}",
+                    arbitrary_word(u, "0123456789.")?,
+                    arbitrary_word(u, "0123456789")?
+                ),
+                _ => arbitrary_word(u, "0123456789.+~")?,
+            };
+            let arch = match format {
+                ArtifactFormat::Deb | ArtifactFormat::Ipk | ArtifactFormat::Rpm => {
+                    Some(arbitrary_word(u, "abcdefghijklmnopqrstuvwxyz0123456789")?)
+                }
+                ArtifactFormat::Msix if u.arbitrary()? => {
+                    Some(arbitrary_word(u, "abcdefghijklmnopqrstuvwxyz0123456789")?)
+                }
+                _ => None,
+            };
+            let name = match format {
+                // these formats use `-` as the separator
+                ArtifactFormat::Rpm | ArtifactFormat::Pkg | ArtifactFormat::MacosPkg => name,
+                _ => name.replace('-', "."),
+            };
+            Ok(Self {
+                name,
+                version,
+                arch,
+                format,
+            })
+        }
+    }
+
+    fn arbitrary_word(u: &mut Unstructured, chars: &str) -> arbitrary::Result<String> {
+        let chars: Vec<char> = chars.chars().collect();
+        let len = u.int_in_range(1..=20)?;
+        let mut s = String::with_capacity(len);
+        for _ in 0..len {
+            s.push(*u.choose(&chars)?);
+        }
+        Ok(s)
+    }
+}
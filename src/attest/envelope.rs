@@ -0,0 +1,177 @@
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use base64ct::Base64;
+use base64ct::Encoding;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::attest::Statement;
+use crate::attest::PAYLOAD_TYPE;
+use crate::sign::Signer;
+use crate::sign::Verifier;
+
+/// Dead Simple Signing Envelope.
+///
+/// https://github.com/secure-systems-lab/dsse/blob/master/envelope.md
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+    /// Base64-encoded payload.
+    pub payload: String,
+    pub payload_type: String,
+    pub signatures: Vec<EnvelopeSignature>,
+}
+
+impl Envelope {
+    pub fn sign<S: Signer>(
+        payload_type: String,
+        payload: &[u8],
+        signer: &S,
+        key_id: String,
+    ) -> Result<Self, Error> {
+        let signature = signer
+            .sign(&pae(&payload_type, payload))
+            .map_err(|_| Error::other("failed to sign the attestation"))?;
+        Ok(Self {
+            payload: Base64::encode_string(payload),
+            payload_type,
+            signatures: vec![EnvelopeSignature {
+                keyid: key_id,
+                sig: Base64::encode_string(&signature),
+            }],
+        })
+    }
+
+    /// Verify the signatures and return decoded payload.
+    pub fn verify<V: Verifier>(&self, verifier: &V) -> Result<Vec<u8>, Error> {
+        let payload = Base64::decode_vec(&self.payload).map_err(Error::other)?;
+        let signatures = self
+            .signatures
+            .iter()
+            .map(|signature| Base64::decode_vec(&signature.sig).map_err(Error::other))
+            .collect::<Result<Vec<_>, _>>()?;
+        verifier
+            .verify_any(&pae(&self.payload_type, &payload), signatures.iter())
+            .map_err(|_| Error::other("attestation signature verification failed"))?;
+        Ok(payload)
+    }
+
+    /// Verify the signatures and decode in-toto statement.
+    pub fn verify_statement<V: Verifier>(&self, verifier: &V) -> Result<Statement, Error> {
+        if self.payload_type != PAYLOAD_TYPE {
+            return Err(Error::other(format!(
+                "unexpected payload type: {}",
+                self.payload_type
+            )));
+        }
+        let payload = self.verify(verifier)?;
+        serde_json::from_slice(&payload).map_err(Error::other)
+    }
+
+    /// Write the envelope as a single line of JSON.
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        serde_json::to_writer(writer.by_ref(), self).map_err(Error::other)?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Write the envelope to `<artifact>.intoto.jsonl` file and return the path of this file.
+    pub fn write_next_to<P: AsRef<Path>>(&self, artifact: P) -> Result<PathBuf, Error> {
+        let path = attestation_path(artifact.as_ref());
+        self.write(File::create(&path)?)?;
+        Ok(path)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EnvelopeSignature {
+    pub keyid: String,
+    /// Base64-encoded signature.
+    pub sig: String,
+}
+
+/// Attestation file path for the artifact.
+pub fn attestation_path(artifact: &Path) -> PathBuf {
+    let mut file_name: OsString = artifact.as_os_str().into();
+    file_name.push(".intoto.jsonl");
+    file_name.into()
+}
+
+/// Pre-authentication encoding.
+fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(payload_type.len() + payload.len() + 32);
+    message.extend_from_slice(b"DSSEv1 ");
+    message.extend_from_slice(payload_type.len().to_string().as_bytes());
+    message.push(b' ');
+    message.extend_from_slice(payload_type.as_bytes());
+    message.push(b' ');
+    message.extend_from_slice(payload.len().to_string().as_bytes());
+    message.push(b' ');
+    message.extend_from_slice(payload);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use pgp::composed::KeyType;
+    use pgp::crypto::hash::HashAlgorithm;
+    use pgp::packet::SignatureType;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::attest::*;
+    use crate::sign::PgpSigner;
+    use crate::sign::PgpVerifier;
+    use crate::test::pgp_keys;
+
+    #[test]
+    fn pae_reference() {
+        assert_eq!(
+            b"DSSEv1 29 http://example.com/HelloWorld 11 hello world".to_vec(),
+            pae("http://example.com/HelloWorld", b"hello world")
+        );
+    }
+
+    #[test]
+    fn sign_verify_statement() {
+        let workdir = TempDir::new().unwrap();
+        let artifact = workdir.path().join("test_1.0_amd64.deb");
+        std::fs::write(&artifact, b"hello world").unwrap();
+        let mut statement = Statement::new(Provenance {
+            build_definition: BuildDefinition {
+                build_type: "https://example.com/wolfpack/v1".into(),
+                external_parameters: [("format".to_string(), "deb".to_string())].into(),
+                internal_parameters: Default::default(),
+                resolved_dependencies: Default::default(),
+            },
+            run_details: RunDetails {
+                builder: Builder {
+                    id: "https://example.com/builder".into(),
+                    version: Default::default(),
+                },
+                metadata: Default::default(),
+            },
+        });
+        statement.add_subject(&artifact).unwrap();
+        assert_eq!(
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9",
+            statement.subject[0].digest["sha256"]
+        );
+        let (signing_key, verifying_key) = pgp_keys(KeyType::Ed25519);
+        let signer = PgpSigner::new(signing_key, SignatureType::Binary, HashAlgorithm::SHA2_256);
+        let verifier = PgpVerifier::new(verifying_key);
+        let envelope = statement.sign(&signer, "test".into()).unwrap();
+        let path = envelope.write_next_to(&artifact).unwrap();
+        assert_eq!(workdir.path().join("test_1.0_amd64.deb.intoto.jsonl"), path);
+        let actual: Envelope = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(statement, actual.verify_statement(&verifier).unwrap());
+        let mut tampered = actual.clone();
+        tampered.payload_type = "text/plain".into();
+        assert!(tampered.verify(&verifier).is_err());
+    }
+}
@@ -0,0 +1,5 @@
+mod envelope;
+mod statement;
+
+pub use self::envelope::*;
+pub use self::statement::*;
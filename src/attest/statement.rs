@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Error;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::attest::Envelope;
+use crate::hash::Sha256Reader;
+use crate::sign::Signer;
+
+/// in-toto attestation statement.
+///
+/// https://github.com/in-toto/attestation/blob/main/spec/v1/statement.md
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Statement {
+    #[serde(rename = "_type")]
+    pub type_: String,
+    pub subject: Vec<ResourceDescriptor>,
+    pub predicate_type: String,
+    pub predicate: Provenance,
+}
+
+impl Statement {
+    pub fn new(provenance: Provenance) -> Self {
+        Self {
+            type_: STATEMENT_TYPE.into(),
+            subject: Default::default(),
+            predicate_type: PROVENANCE_TYPE.into(),
+            predicate: provenance,
+        }
+    }
+
+    /// Add package file as the subject of the attestation.
+    pub fn add_subject<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .and_then(|x| x.to_str())
+            .ok_or_else(|| Error::other(format!("invalid file name: {}", path.display())))?;
+        let (sha256, _) = Sha256Reader::new(File::open(path)?).digest()?;
+        self.subject.push(ResourceDescriptor {
+            uri: None,
+            name: Some(name.into()),
+            digest: [("sha256".to_string(), sha256.to_string())].into(),
+        });
+        Ok(())
+    }
+
+    /// Sign the statement and wrap it in DSSE envelope.
+    pub fn sign<S: Signer>(&self, signer: &S, key_id: String) -> Result<Envelope, Error> {
+        let payload = serde_json::to_vec(self).map_err(Error::other)?;
+        Envelope::sign(PAYLOAD_TYPE.into(), &payload, signer, key_id)
+    }
+}
+
+/// SLSA build provenance.
+///
+/// https://slsa.dev/spec/v1.0/provenance
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    pub build_definition: BuildDefinition,
+    pub run_details: RunDetails,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildDefinition {
+    /// URI that describes how the build parameters are interpreted.
+    pub build_type: String,
+    /// Parameters that the user has control over, e.g. package name and target format.
+    pub external_parameters: BTreeMap<String, String>,
+    /// Parameters that the builder has control over.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub internal_parameters: BTreeMap<String, String>,
+    /// Sources and other inputs with their digests.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resolved_dependencies: Vec<ResourceDescriptor>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RunDetails {
+    pub builder: Builder,
+    #[serde(default)]
+    pub metadata: BuildMetadata,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Builder {
+    /// URI that identifies the builder.
+    pub id: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub version: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invocation_id: Option<String>,
+    /// RFC 3339 timestamp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_on: Option<String>,
+    /// RFC 3339 timestamp.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_on: Option<String>,
+}
+
+/// https://github.com/in-toto/attestation/blob/main/spec/v1/resource_descriptor.md
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceDescriptor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Algorithm name to hex-encoded digest mapping, e.g. `sha256` or `gitCommit`.
+    pub digest: BTreeMap<String, String>,
+}
+
+pub const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+pub const PROVENANCE_TYPE: &str = "https://slsa.dev/provenance/v1";
+pub const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
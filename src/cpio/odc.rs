@@ -1,8 +1,10 @@
 use std::ffi::CStr;
 use std::ffi::OsStr;
+use std::ffi::OsString;
 use std::fs::File;
 use std::fs::Metadata;
 use std::io::Error;
+use std::io::ErrorKind;
 use std::io::Read;
 use std::io::Take;
 use std::io::Write;
@@ -15,16 +17,21 @@ use std::str::from_utf8;
 use normalize_path::NormalizePath;
 use walkdir::WalkDir;
 
+use crate::archive::ExtendedMetadata;
+use crate::archive::InodeAllocator;
+use crate::archive::ModePolicy;
+use crate::archive::FILE_TYPE_MASK;
+
 pub struct CpioBuilder<W: Write> {
     writer: Box<W>,
-    max_inode: u32,
+    inodes: InodeAllocator,
 }
 
 impl<W: Write> CpioBuilder<W> {
     pub fn new(writer: W) -> Self {
         Self {
             writer: Box::new(writer),
-            max_inode: 0,
+            inodes: InodeAllocator::new(MAX_6),
         }
     }
 
@@ -59,9 +66,21 @@ impl<W: Write> CpioBuilder<W> {
     }
 
     pub fn from_directory<P: AsRef<Path>>(writer: W, directory: P) -> Result<W, Error> {
+        Self::from_directory_with_mode_policy(writer, directory, &Default::default())
+    }
+
+    /// Archive the files from `directory`.
+    ///
+    /// Extended attributes of each file are stored in AppleDouble `._<name>` member that
+    /// immediately follows the file, the same way macOS `ditto` does. File flags are not stored.
+    pub fn from_directory_with_mode_policy<P: AsRef<Path>>(
+        writer: W,
+        directory: P,
+        mode_policy: &ModePolicy,
+    ) -> Result<W, Error> {
         let directory = directory.as_ref();
         let mut builder = Self::new(writer);
-        for entry in WalkDir::new(directory).into_iter() {
+        for entry in WalkDir::new(directory).sort_by_file_name().into_iter() {
             let entry = entry?;
             let entry_path = entry
                 .path()
@@ -73,8 +92,24 @@ impl<W: Write> CpioBuilder<W> {
                 continue;
             }
             let metadata = entry.path().metadata()?;
-            let header: OdcHeader = metadata.try_into()?;
-            builder.write_entry(header, entry_path, File::open(entry.path())?)?;
+            let mode = mode_policy.mode(&entry_path, &metadata);
+            let extended_metadata = ExtendedMetadata::read(entry.path(), &metadata)?;
+            let mut header: OdcHeader = metadata.try_into()?;
+            header.mode = (header.mode & FILE_TYPE_MASK) | mode;
+            builder.write_entry(header.clone(), &entry_path, File::open(entry.path())?)?;
+            if let Some(apple_double) = extended_metadata.to_apple_double() {
+                let mut file_name = OsString::from("._");
+                file_name.push(entry_path.file_name().unwrap_or_default());
+                // Regular file with 0644 permissions.
+                header.mode = 0o100644;
+                header.nlink = 1;
+                header.file_size = apple_double.len() as u64;
+                builder.write_entry(
+                    header,
+                    entry_path.with_file_name(file_name),
+                    apple_double.as_slice(),
+                )?;
+            }
         }
         let writer = builder.finish()?;
         Ok(writer)
@@ -119,15 +154,9 @@ impl<W: Write> CpioBuilder<W> {
         }
         // +1 due to null byte
         header.name_len = (name_len + 1) as u32;
-        header.ino = self.next_inode();
+        header.ino = self.inodes.allocate(name)?;
         Ok(())
     }
-
-    fn next_inode(&mut self) -> u32 {
-        let old = self.max_inode;
-        self.max_inode += 1;
-        old
-    }
 }
 
 /* TODO ????
@@ -242,9 +271,14 @@ pub struct OdcHeader {
 impl OdcHeader {
     fn read_some<R: Read>(mut reader: R) -> Result<Option<Self>, Error> {
         let mut bytes = [0_u8; ODC_HEADER_LEN];
-        let nread = reader.read(&mut bytes[..])?;
-        if nread == 0 {
-            return Ok(None);
+        // Decoders may return less bytes than requested.
+        let mut nread = 0;
+        while nread != bytes.len() {
+            match reader.read(&mut bytes[nread..])? {
+                0 if nread == 0 => return Ok(None),
+                0 => return Err(ErrorKind::UnexpectedEof.into()),
+                n => nread += n,
+            }
         }
         let header = Self::read(&bytes[..])?;
         Ok(Some(header))
@@ -382,6 +416,8 @@ mod tests {
 
     use arbitrary::Arbitrary;
     use arbitrary::Unstructured;
+    use std::fs::create_dir_all;
+
     use arbtest::arbtest;
     use tempfile::TempDir;
 
@@ -390,6 +426,41 @@ mod tests {
 
     // TODO compare output to GNU cpio
 
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn xattrs_from_directory() {
+        let workdir = TempDir::new().unwrap();
+        create_dir_all(workdir.path().join("dir")).unwrap();
+        let file = workdir.path().join("dir/file");
+        std::fs::write(&file, "hello").unwrap();
+        std::fs::write(workdir.path().join("other"), "world").unwrap();
+        xattr::set(&file, "user.comment", b"hello").unwrap();
+        let bytes = CpioBuilder::from_directory(Vec::new(), workdir.path()).unwrap();
+        let mut archive = CpioArchive::new(bytes.as_slice());
+        let mut entries = Vec::new();
+        for entry in archive.iter() {
+            let mut entry = entry.unwrap();
+            let mut contents = Vec::new();
+            entry.reader.read_to_end(&mut contents).unwrap();
+            entries.push((entry.name, entry.header.mode, contents));
+        }
+        let names: Vec<_> = entries.iter().map(|(name, ..)| name.as_path()).collect();
+        assert_eq!(
+            vec![
+                Path::new("dir/file"),
+                Path::new("dir/._file"),
+                Path::new("other")
+            ],
+            names
+        );
+        assert_eq!(0o100644, entries[1].1);
+        let metadata = ExtendedMetadata::from_apple_double(&entries[1].2).unwrap();
+        assert_eq!(
+            vec![("user.comment".into(), b"hello".to_vec())],
+            metadata.xattrs
+        );
+    }
+
     #[test]
     fn cpio_write_read() {
         let workdir = TempDir::new().unwrap();
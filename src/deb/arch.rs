@@ -0,0 +1,455 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+use crate::deb::Error;
+
+/// Debian architecture, e.g. `amd64`, `arm64`, `musl-linux-amd64` or `all`.
+///
+/// https://www.debian.org/doc/debian-policy/ch-customized-programs.html#architecture-specification-strings
+#[derive(Clone, PartialEq, Eq, Hash, Debug, PartialOrd, Ord)]
+pub struct Arch(String);
+
+impl Arch {
+    pub fn new(name: String) -> Result<Self, Error> {
+        // Wildcards are not architectures.
+        if !is_valid_arch_name(&name) || name.split('-').any(|component| component == ANY) {
+            return Err(Error::Arch(name));
+        }
+        Ok(Self(name))
+    }
+
+    /// Architecture-independent packages.
+    pub fn all() -> Self {
+        Self(ALL.into())
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.0 == ALL
+    }
+
+    /// ABI part of the architecture tuple, e.g. `base` for `amd64`.
+    pub fn abi(&self) -> &str {
+        self.tuple()[0]
+    }
+
+    /// C library part of the architecture tuple, e.g. `musl` for `musl-linux-amd64`.
+    pub fn libc(&self) -> &str {
+        self.tuple()[1]
+    }
+
+    /// Operating system part of the architecture tuple, e.g. `linux` for `amd64`.
+    pub fn os(&self) -> &str {
+        self.tuple()[2]
+    }
+
+    /// CPU part of the architecture tuple, e.g. `amd64` for `kfreebsd-amd64`.
+    pub fn cpu(&self) -> &str {
+        self.tuple()[3]
+    }
+
+    /// `<abi>-<libc>-<os>-<cpu>` tuple.
+    ///
+    /// Known names are mapped the same way as in dpkg's `tupletable`, e.g. `armhf` is
+    /// `eabihf-gnu-linux-arm` and `kfreebsd-amd64` is `base-gnu-kfreebsd-amd64`. For unknown
+    /// names the missing leading components default to `base-gnu-linux`.
+    fn tuple(&self) -> [&str; 4] {
+        if let Some((_, tuple)) = TUPLES.iter().find(|(name, _)| *name == self.0) {
+            return *tuple;
+        }
+        for (prefix, [abi, libc, os]) in TUPLE_PREFIXES.iter() {
+            if let Some(cpu) = self.0.strip_prefix(prefix) {
+                if !cpu.contains('-') {
+                    return [abi, libc, os, cpu];
+                }
+            }
+        }
+        let mut tuple = [BASE, GNU, LINUX, ""];
+        let components: Vec<&str> = self.0.rsplitn(4, '-').collect();
+        for (i, component) in components.into_iter().enumerate() {
+            tuple[3 - i] = component;
+        }
+        tuple
+    }
+
+    /// Packages for this architecture can be installed on the `host`.
+    pub fn is_installable_on(&self, host: &Arch) -> bool {
+        self.is_all() || self == host
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Arch {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Arch {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::new(value.to_string())
+    }
+}
+
+/// Architecture name or wildcard, e.g. `any`, `all`, `linux-any`, `any-amd64` or `arm64`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ArchPattern {
+    /// Any architecture except `all`.
+    Any,
+    /// Architecture-independent packages.
+    All,
+    /// `<abi>-<libc>-<os>-<cpu>` tuple where `None` means `any`.
+    ///
+    /// The missing leading components are `any`, i.e. `linux-any` is `any-any-linux-any`.
+    Tuple {
+        abi: Option<String>,
+        libc: Option<String>,
+        os: Option<String>,
+        cpu: Option<String>,
+    },
+    /// Exact architecture name.
+    Exact(Arch),
+}
+
+impl ArchPattern {
+    pub fn matches(&self, arch: &Arch) -> bool {
+        match self {
+            Self::Any => !arch.is_all(),
+            Self::All => arch.is_all(),
+            Self::Tuple { abi, libc, os, cpu } => {
+                !arch.is_all()
+                    && [abi, libc, os, cpu].into_iter().zip(arch.tuple()).all(
+                        |(pattern, component)| {
+                            pattern
+                                .as_deref()
+                                .is_none_or(|pattern| pattern == component)
+                        },
+                    )
+            }
+            Self::Exact(other) => other == arch,
+        }
+    }
+}
+
+impl Display for ArchPattern {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::Any => f.write_str(ANY),
+            Self::All => f.write_str(ALL),
+            Self::Tuple { abi, libc, os, cpu } => {
+                let components = [abi, libc, os, cpu];
+                // Omit leading `any` components but keep at least `<os>-<cpu>` and at least one
+                // `any` to distinguish the pattern from the exact architecture name.
+                let start = components[..2]
+                    .iter()
+                    .position(|x| x.is_some())
+                    .unwrap_or(2);
+                let start = if components[start..].iter().any(|x| x.is_none()) {
+                    start
+                } else {
+                    components.iter().rposition(|x| x.is_none()).unwrap_or(0)
+                };
+                for (i, component) in components[start..].iter().enumerate() {
+                    if i != 0 {
+                        f.write_str("-")?;
+                    }
+                    f.write_str(component.as_deref().unwrap_or(ANY))?;
+                }
+                Ok(())
+            }
+            Self::Exact(arch) => Display::fmt(arch, f),
+        }
+    }
+}
+
+impl FromStr for ArchPattern {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if !is_valid_arch_name(value) {
+            return Err(Error::Arch(value.into()));
+        }
+        match value {
+            ANY => return Ok(Self::Any),
+            ALL => return Ok(Self::All),
+            _ => {}
+        }
+        let components: Vec<&str> = value.split('-').collect();
+        if components.len() == 1 || !components.contains(&ANY) {
+            return Ok(Self::Exact(value.parse()?));
+        }
+        if components.len() > 4 {
+            return Err(Error::Arch(value.into()));
+        }
+        let mut tuple: [Option<String>; 4] = Default::default();
+        for (i, component) in components.iter().rev().enumerate() {
+            tuple[3 - i] = (*component != ANY).then(|| component.to_string());
+        }
+        let [abi, libc, os, cpu] = tuple;
+        Ok(Self::Tuple { abi, libc, os, cpu })
+    }
+}
+
+/// Value of the `Multi-Arch` field.
+///
+/// https://wiki.debian.org/Multiarch/HOWTO
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[cfg_attr(test, derive(arbitrary::Arbitrary))]
+pub enum MultiArch {
+    /// Satisfies dependencies only of the packages of the same architecture.
+    #[default]
+    No,
+    /// Can be co-installed with the same package of a different architecture.
+    Same,
+    /// Satisfies dependencies (including `pkg:any`) of the packages of any architecture.
+    Foreign,
+    /// Satisfies `pkg:any` dependencies of the packages of any architecture.
+    Allowed,
+}
+
+impl MultiArch {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::No => "no",
+            Self::Same => "same",
+            Self::Foreign => "foreign",
+            Self::Allowed => "allowed",
+        }
+    }
+}
+
+impl Display for MultiArch {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MultiArch {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "no" => Ok(Self::No),
+            "same" => Ok(Self::Same),
+            "foreign" => Ok(Self::Foreign),
+            "allowed" => Ok(Self::Allowed),
+            other => Err(Error::FieldValue(other.into())),
+        }
+    }
+}
+
+fn is_valid_arch_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '-')
+}
+
+const ANY: &str = "any";
+const ALL: &str = "all";
+const LINUX: &str = "linux";
+const GNU: &str = "gnu";
+const BASE: &str = "base";
+
+/// Architectures with non-trivial tuples from dpkg's `tupletable`.
+const TUPLES: [(&str, [&str; 4]); 17] = [
+    ("uclibc-linux-armel", ["eabi", "uclibc", "linux", "arm"]),
+    ("musl-linux-armhf", ["eabihf", "musl", "linux", "arm"]),
+    ("arm64ilp32", ["ilp32", GNU, LINUX, "arm64"]),
+    ("armhf", ["eabihf", GNU, LINUX, "arm"]),
+    ("armel", ["eabi", GNU, LINUX, "arm"]),
+    ("mipsn32r6el", ["abin32", GNU, LINUX, "mips64r6el"]),
+    ("mipsn32r6", ["abin32", GNU, LINUX, "mips64r6"]),
+    ("mipsn32el", ["abin32", GNU, LINUX, "mips64el"]),
+    ("mipsn32", ["abin32", GNU, LINUX, "mips64"]),
+    ("mips64r6el", ["abi64", GNU, LINUX, "mips64r6el"]),
+    ("mips64r6", ["abi64", GNU, LINUX, "mips64r6"]),
+    ("mips64el", ["abi64", GNU, LINUX, "mips64el"]),
+    ("mips64", ["abi64", GNU, LINUX, "mips64"]),
+    ("powerpcspe", ["spe", GNU, LINUX, "powerpc"]),
+    ("x32", ["x32", GNU, LINUX, "amd64"]),
+    ("kfreebsd-armhf", ["eabihf", GNU, "kfreebsd", "arm"]),
+    ("uclinux-armel", ["eabi", "uclibc", "uclinux", "arm"]),
+];
+
+/// `<os>-<cpu>` architectures from dpkg's `tupletable` and their `<abi>-<libc>-<os>` prefixes.
+const TUPLE_PREFIXES: [(&str, [&str; 3]); 15] = [
+    ("uclibc-linux-", [BASE, "uclibc", LINUX]),
+    ("musl-linux-", [BASE, "musl", LINUX]),
+    ("kfreebsd-", [BASE, GNU, "kfreebsd"]),
+    ("knetbsd-", [BASE, GNU, "knetbsd"]),
+    ("kopensolaris-", [BASE, GNU, "kopensolaris"]),
+    ("hurd-", [BASE, GNU, "hurd"]),
+    ("dragonflybsd-", [BASE, "bsd", "dragonflybsd"]),
+    ("freebsd-", [BASE, "bsd", "freebsd"]),
+    ("openbsd-", [BASE, "bsd", "openbsd"]),
+    ("netbsd-", [BASE, "bsd", "netbsd"]),
+    ("darwin-", [BASE, "bsd", "darwin"]),
+    ("aix-", [BASE, "sysv", "aix"]),
+    ("solaris-", [BASE, "sysv", "solaris"]),
+    ("uclinux-", [BASE, "uclibc", "uclinux"]),
+    ("mint-", [BASE, "tos", "mint"]),
+];
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::Arbitrary;
+    use arbitrary::Unstructured;
+    use arbtest::arbtest;
+
+    use super::*;
+
+    #[test]
+    fn arch_tuple() {
+        let arch: Arch = "amd64".parse().unwrap();
+        assert_eq!(("linux", "amd64"), (arch.os(), arch.cpu()));
+        let arch: Arch = "kfreebsd-amd64".parse().unwrap();
+        assert_eq!(("kfreebsd", "amd64"), (arch.os(), arch.cpu()));
+        let arch: Arch = "musl-linux-arm64".parse().unwrap();
+        assert_eq!(("linux", "arm64"), (arch.os(), arch.cpu()));
+        assert_eq!(("base", "musl"), (arch.abi(), arch.libc()));
+        let arch: Arch = "amd64".parse().unwrap();
+        assert_eq!(("base", "gnu"), (arch.abi(), arch.libc()));
+        let arch: Arch = "armhf".parse().unwrap();
+        assert_eq!(["eabihf", "gnu", "linux", "arm"], arch.tuple());
+        let arch: Arch = "armel".parse().unwrap();
+        assert_eq!(["eabi", "gnu", "linux", "arm"], arch.tuple());
+        let arch: Arch = "x32".parse().unwrap();
+        assert_eq!(["x32", "gnu", "linux", "amd64"], arch.tuple());
+        let arch: Arch = "musl-linux-armhf".parse().unwrap();
+        assert_eq!(["eabihf", "musl", "linux", "arm"], arch.tuple());
+        let arch: Arch = "freebsd-amd64".parse().unwrap();
+        assert_eq!(["base", "bsd", "freebsd", "amd64"], arch.tuple());
+        assert!("any".parse::<Arch>().is_err());
+        assert!("linux-any".parse::<Arch>().is_err());
+        assert!("any-amd64".parse::<Arch>().is_err());
+        assert!("musl-any-amd64".parse::<Arch>().is_err());
+        assert!("".parse::<Arch>().is_err());
+        assert!("AMD64".parse::<Arch>().is_err());
+    }
+
+    #[test]
+    fn wildcards() {
+        let amd64: Arch = "amd64".parse().unwrap();
+        let arm64: Arch = "arm64".parse().unwrap();
+        let hurd: Arch = "hurd-i386".parse().unwrap();
+        let musl: Arch = "musl-linux-amd64".parse().unwrap();
+        let armhf: Arch = "armhf".parse().unwrap();
+        let armel: Arch = "armel".parse().unwrap();
+        let x32: Arch = "x32".parse().unwrap();
+        let all = Arch::all();
+        let matches =
+            |pattern: &str, arch: &Arch| pattern.parse::<ArchPattern>().unwrap().matches(arch);
+        assert!(matches("any", &amd64));
+        assert!(matches("any", &hurd));
+        assert!(!matches("any", &all));
+        assert!(matches("all", &all));
+        assert!(!matches("all", &amd64));
+        assert!(matches("linux-any", &amd64));
+        assert!(matches("linux-any", &arm64));
+        assert!(matches("linux-any", &musl));
+        assert!(!matches("linux-any", &hurd));
+        assert!(!matches("linux-any", &all));
+        assert!(matches("any-amd64", &amd64));
+        assert!(matches("any-amd64", &musl));
+        assert!(!matches("any-amd64", &arm64));
+        assert!(matches("any-i386", &hurd));
+        assert!(matches("hurd-any", &hurd));
+        assert!(matches("musl-linux-any", &musl));
+        assert!(!matches("musl-linux-any", &amd64));
+        assert!(matches("gnu-linux-any", &amd64));
+        assert!(!matches("gnu-linux-any", &musl));
+        assert!(matches("any-linux-any", &musl));
+        assert!(matches("base-any-any-amd64", &musl));
+        assert!("any-any-any-any-amd64".parse::<ArchPattern>().is_err());
+        assert!(matches("any-arm", &armhf));
+        assert!(matches("any-arm", &armel));
+        assert!(!matches("any-arm", &arm64));
+        assert!(!matches("any-armhf", &armhf));
+        assert!(matches("linux-any", &armhf));
+        assert!(matches("eabihf-any-any-any", &armhf));
+        assert!(!matches("eabihf-any-any-any", &armel));
+        assert!(matches("any-amd64", &x32));
+        assert!(!matches("base-any-any-amd64", &x32));
+        assert!(matches("amd64", &amd64));
+        assert!(!matches("amd64", &musl));
+        assert!(!matches("amd64", &x32));
+        assert!(all.is_installable_on(&amd64));
+        assert!(!arm64.is_installable_on(&amd64));
+    }
+
+    #[test]
+    fn multi_arch_display_parse() {
+        arbtest(|u| {
+            let expected: MultiArch = u.arbitrary()?;
+            let string = expected.to_string();
+            let actual: MultiArch = string.parse().unwrap();
+            assert_eq!(expected, actual);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn pattern_display_parse() {
+        arbtest(|u| {
+            let expected: ArchPattern = u.arbitrary()?;
+            let string = expected.to_string();
+            let actual: ArchPattern = string.parse().unwrap();
+            assert_eq!(expected, actual, "string = {:?}", string);
+            Ok(())
+        });
+    }
+
+    impl<'a> Arbitrary<'a> for Arch {
+        fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+            Ok(Self(u.choose(&ARCHITECTURES)?.to_string()))
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for ArchPattern {
+        fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+            Ok(match u.int_in_range(0..=3)? {
+                0 => Self::Any,
+                1 => Self::All,
+                2 => {
+                    // At least one of the components is `any`.
+                    let mut component = |choices: &[&str]| -> arbitrary::Result<Option<String>> {
+                        Ok(if u.arbitrary()? {
+                            Some(u.choose(choices)?.to_string())
+                        } else {
+                            None
+                        })
+                    };
+                    let abi = component(&["base", "eabihf"])?;
+                    let libc = component(&["gnu", "musl"])?;
+                    let os = component(&["linux", "hurd"])?;
+                    let cpu = component(&["amd64", "i386"])?;
+                    if [&abi, &libc, &os, &cpu].iter().all(|x| x.is_some()) {
+                        Self::Tuple {
+                            abi,
+                            libc,
+                            os,
+                            cpu: None,
+                        }
+                    } else {
+                        Self::Tuple { abi, libc, os, cpu }
+                    }
+                }
+                _ => Self::Exact(u.arbitrary()?),
+            })
+        }
+    }
+
+    const ARCHITECTURES: [&str; 6] = [
+        "amd64",
+        "arm64",
+        "i386",
+        "kfreebsd-amd64",
+        "hurd-i386",
+        "musl-linux-amd64",
+    ];
+}
@@ -0,0 +1,722 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+use crate::deb::Arch;
+use crate::deb::ArchPattern;
+use crate::deb::Error;
+use crate::deb::MultiArch;
+use crate::deb::Package;
+use crate::deb::PackageName;
+use crate::deb::PackageVersion;
+
+/// Comma-separated list of dependencies, i.e. the value of `Depends`, `Pre-Depends` etc.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Dependencies(pub Vec<DependencyChoice>);
+
+impl Dependencies {
+    /// Dependencies that are relevant on the `host` architecture.
+    pub fn enabled_on<'a>(&'a self, host: &'a Arch) -> impl Iterator<Item = DependencyChoice> + 'a {
+        self.0
+            .iter()
+            .map(|choice| choice.enabled_on(host))
+            .filter(|choice| !choice.0.is_empty())
+    }
+}
+
+impl Display for Dependencies {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let mut iter = self.0.iter();
+        if let Some(choice) = iter.next() {
+            write!(f, "{}", choice)?;
+        }
+        for choice in iter {
+            write!(f, ", {}", choice)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Dependencies {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut choices = Vec::new();
+        for choice in value.split(',') {
+            let choice = choice.trim();
+            if choice.is_empty() {
+                continue;
+            }
+            choices.push(choice.parse()?);
+        }
+        Ok(Self(choices))
+    }
+}
+
+/// Alternative dependencies separated by `|`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DependencyChoice(pub Vec<Dependency>);
+
+impl DependencyChoice {
+    /// Alternatives that are relevant on the `host` architecture.
+    pub fn enabled_on(&self, host: &Arch) -> Self {
+        Self(
+            self.0
+                .iter()
+                .filter(|dep| dep.is_enabled_on(host))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Find the first package that satisfies any of the alternatives.
+    pub fn find<'a, I>(&self, packages: I, host: &Arch) -> Option<&'a Package>
+    where
+        I: IntoIterator<Item = &'a Package>,
+        I::IntoIter: Clone,
+    {
+        let packages = packages.into_iter();
+        self.0
+            .iter()
+            .find_map(|dep| packages.clone().find(|package| dep.matches(package, host)))
+    }
+}
+
+impl Display for DependencyChoice {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let mut iter = self.0.iter();
+        if let Some(dep) = iter.next() {
+            write!(f, "{}", dep)?;
+        }
+        for dep in iter {
+            write!(f, " | {}", dep)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DependencyChoice {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let alternatives = value
+            .split('|')
+            .map(|dep| dep.trim().parse())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self(alternatives))
+    }
+}
+
+/// Single dependency, e.g. `libc6:any (>= 2.36) [linux-any]`.
+///
+/// Build profiles (`<...>`) are parsed but ignored.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Dependency {
+    pub name: PackageName,
+    pub arch: Option<ArchQualifier>,
+    pub version: Option<VersionConstraint>,
+    pub arch_restrictions: Vec<ArchRestriction>,
+}
+
+impl Dependency {
+    /// Check architecture restrictions (`[...]`) against the `host` architecture.
+    pub fn is_enabled_on(&self, host: &Arch) -> bool {
+        if self.arch_restrictions.is_empty() {
+            return true;
+        }
+        let mut iter = self.arch_restrictions.iter();
+        if self.arch_restrictions[0].negated {
+            // `[!hurd-any !kfreebsd-any]`
+            iter.all(|r| !r.pattern.matches(host))
+        } else {
+            // `[linux-any hurd-any]`
+            iter.any(|r| r.pattern.matches(host))
+        }
+    }
+
+    /// Check whether `package` satisfies this dependency of a package built for `host`
+    /// architecture.
+    pub fn matches(&self, package: &Package, host: &Arch) -> bool {
+        if package.name() != &self.name {
+            return false;
+        }
+        if let Some(version) = self.version.as_ref() {
+            if !version.matches(&package.version) {
+                return false;
+            }
+        }
+        let Ok(arch) = package.arch() else {
+            return false;
+        };
+        let Ok(multi_arch) = package.multi_arch() else {
+            return false;
+        };
+        match self.arch.as_ref() {
+            None => arch.is_installable_on(host) || multi_arch == MultiArch::Foreign,
+            Some(ArchQualifier::Native) => arch.is_installable_on(host),
+            Some(ArchQualifier::Any) => {
+                matches!(multi_arch, MultiArch::Allowed | MultiArch::Foreign)
+            }
+            Some(ArchQualifier::Arch(other)) => &arch == other,
+        }
+    }
+}
+
+impl Display for Dependency {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(arch) = self.arch.as_ref() {
+            write!(f, ":{}", arch)?;
+        }
+        if let Some(version) = self.version.as_ref() {
+            write!(f, " ({})", version)?;
+        }
+        if !self.arch_restrictions.is_empty() {
+            f.write_str(" [")?;
+            let mut iter = self.arch_restrictions.iter();
+            if let Some(r) = iter.next() {
+                write!(f, "{}", r)?;
+            }
+            for r in iter {
+                write!(f, " {}", r)?;
+            }
+            f.write_str("]")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Dependency {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::Dependency(value.into());
+        let value = value.trim();
+        // Build profiles follow version constraint and architecture restrictions.
+        let start = value.rfind([')', ']']).unwrap_or(0);
+        let value = match value[start..].find('<') {
+            Some(i) => value[..start + i].trim_end(),
+            None => value,
+        };
+        let (value, arch_restrictions) = match value.split_once('[') {
+            Some((value, restrictions)) => {
+                let restrictions = restrictions
+                    .trim_end()
+                    .strip_suffix(']')
+                    .ok_or_else(invalid)?;
+                let restrictions = restrictions
+                    .split_whitespace()
+                    .map(|r| r.parse())
+                    .collect::<Result<Vec<ArchRestriction>, _>>()?;
+                if restrictions.is_empty()
+                    || restrictions
+                        .iter()
+                        .any(|r| r.negated != restrictions[0].negated)
+                {
+                    return Err(invalid());
+                }
+                (value.trim_end(), restrictions)
+            }
+            None => (value, Vec::new()),
+        };
+        let (value, version) = match value.split_once('(') {
+            Some((value, version)) => {
+                let version = version.trim_end().strip_suffix(')').ok_or_else(invalid)?;
+                (value.trim_end(), Some(version.parse()?))
+            }
+            None => (value, None),
+        };
+        if value.contains(char::is_whitespace) {
+            return Err(invalid());
+        }
+        let (name, arch) = match value.split_once(':') {
+            Some((name, arch)) => (name, Some(arch.parse()?)),
+            None => (value, None),
+        };
+        Ok(Self {
+            name: name.parse()?,
+            arch,
+            version,
+            arch_restrictions,
+        })
+    }
+}
+
+/// Architecture qualifier, i.e. `:any`, `:native` or `:<arch>` suffix.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ArchQualifier {
+    /// Satisfied by `Multi-Arch: allowed` packages of any architecture.
+    Any,
+    /// Satisfied by the packages of the host architecture only.
+    Native,
+    /// Satisfied by the packages of the specified architecture only.
+    Arch(Arch),
+}
+
+impl Display for ArchQualifier {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::Any => f.write_str("any"),
+            Self::Native => f.write_str("native"),
+            Self::Arch(arch) => write!(f, "{}", arch),
+        }
+    }
+}
+
+impl FromStr for ArchQualifier {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "any" => Ok(Self::Any),
+            "native" => Ok(Self::Native),
+            other => Ok(Self::Arch(other.parse()?)),
+        }
+    }
+}
+
+/// Architecture restriction, e.g. `linux-any` or `!hurd-any`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ArchRestriction {
+    pub negated: bool,
+    pub pattern: ArchPattern,
+}
+
+impl Display for ArchRestriction {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if self.negated {
+            f.write_str("!")?;
+        }
+        write!(f, "{}", self.pattern)
+    }
+}
+
+impl FromStr for ArchRestriction {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (negated, pattern) = match value.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, value),
+        };
+        Ok(Self {
+            negated,
+            pattern: pattern.parse()?,
+        })
+    }
+}
+
+/// Version constraint, e.g. `>= 1.0`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct VersionConstraint {
+    pub op: VersionOp,
+    pub version: PackageVersion,
+}
+
+impl VersionConstraint {
+    pub fn matches(&self, version: &PackageVersion) -> bool {
+        use std::cmp::Ordering::*;
+        let ordering = version.cmp(&self.version);
+        match self.op {
+            VersionOp::Lt => ordering == Less,
+            VersionOp::Le => ordering != Greater,
+            VersionOp::Eq => ordering == Equal,
+            VersionOp::Ge => ordering != Less,
+            VersionOp::Gt => ordering == Greater,
+        }
+    }
+}
+
+impl Display for VersionConstraint {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{} {}", self.op, self.version)
+    }
+}
+
+impl FromStr for VersionConstraint {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let i = value
+            .find(|ch| !matches!(ch, '<' | '=' | '>'))
+            .ok_or_else(|| Error::Dependency(value.into()))?;
+        let (op, version) = value.split_at(i);
+        Ok(Self {
+            op: op.parse()?,
+            version: PackageVersion::new(version.trim_start())?,
+        })
+    }
+}
+
+/// Intersection of version constraints.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct VersionRange {
+    /// Lower bound and whether it is inclusive.
+    pub lower: Option<(PackageVersion, bool)>,
+    /// Upper bound and whether it is inclusive.
+    pub upper: Option<(PackageVersion, bool)>,
+}
+
+impl VersionRange {
+    /// Range that contains all versions.
+    pub fn any() -> Self {
+        Self::default()
+    }
+
+    pub fn intersect(&self, other: &Self) -> Self {
+        use std::cmp::Ordering::*;
+        let lower = match (self.lower.as_ref(), other.lower.as_ref()) {
+            (Some(a), Some(b)) => match a.0.cmp(&b.0) {
+                Less => Some(b.clone()),
+                Greater => Some(a.clone()),
+                Equal => Some((a.0.clone(), a.1 && b.1)),
+            },
+            (a, b) => a.or(b).cloned(),
+        };
+        let upper = match (self.upper.as_ref(), other.upper.as_ref()) {
+            (Some(a), Some(b)) => match a.0.cmp(&b.0) {
+                Less => Some(a.clone()),
+                Greater => Some(b.clone()),
+                Equal => Some((a.0.clone(), a.1 && b.1)),
+            },
+            (a, b) => a.or(b).cloned(),
+        };
+        Self { lower, upper }
+    }
+
+    /// No version satisfies the range.
+    pub fn is_empty(&self) -> bool {
+        use std::cmp::Ordering::*;
+        match (self.lower.as_ref(), self.upper.as_ref()) {
+            (Some((lower, lower_inclusive)), Some((upper, upper_inclusive))) => {
+                match lower.cmp(upper) {
+                    Less => false,
+                    Equal => !(*lower_inclusive && *upper_inclusive),
+                    Greater => true,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    pub fn contains(&self, version: &PackageVersion) -> bool {
+        let lower = match self.lower.as_ref() {
+            Some((lower, true)) => version >= lower,
+            Some((lower, false)) => version > lower,
+            None => true,
+        };
+        let upper = match self.upper.as_ref() {
+            Some((upper, true)) => version <= upper,
+            Some((upper, false)) => version < upper,
+            None => true,
+        };
+        lower && upper
+    }
+}
+
+impl From<&VersionConstraint> for VersionRange {
+    fn from(other: &VersionConstraint) -> Self {
+        let version = other.version.clone();
+        match other.op {
+            VersionOp::Lt => Self {
+                lower: None,
+                upper: Some((version, false)),
+            },
+            VersionOp::Le => Self {
+                lower: None,
+                upper: Some((version, true)),
+            },
+            VersionOp::Eq => Self {
+                lower: Some((version.clone(), true)),
+                upper: Some((version, true)),
+            },
+            VersionOp::Ge => Self {
+                lower: Some((version, true)),
+                upper: None,
+            },
+            VersionOp::Gt => Self {
+                lower: Some((version, false)),
+                upper: None,
+            },
+        }
+    }
+}
+
+impl Display for VersionRange {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("none");
+        }
+        match (self.lower.as_ref(), self.upper.as_ref()) {
+            (Some((lower, true)), Some((upper, true))) if lower == upper => {
+                write!(f, "= {}", lower)
+            }
+            (lower, upper) => {
+                let lower = lower.map(|(version, inclusive)| {
+                    let op = if *inclusive {
+                        VersionOp::Ge
+                    } else {
+                        VersionOp::Gt
+                    };
+                    format!("{} {}", op, version)
+                });
+                let upper = upper.map(|(version, inclusive)| {
+                    let op = if *inclusive {
+                        VersionOp::Le
+                    } else {
+                        VersionOp::Lt
+                    };
+                    format!("{} {}", op, version)
+                });
+                match (lower, upper) {
+                    (Some(lower), Some(upper)) => write!(f, "{}, {}", lower, upper),
+                    (Some(bound), None) | (None, Some(bound)) => f.write_str(&bound),
+                    (None, None) => f.write_str("any"),
+                }
+            }
+        }
+    }
+}
+
+/// Version comparison operator.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(test, derive(arbitrary::Arbitrary))]
+pub enum VersionOp {
+    /// `<<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `=`
+    Eq,
+    /// `>=`
+    Ge,
+    /// `>>`
+    Gt,
+}
+
+impl VersionOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lt => "<<",
+            Self::Le => "<=",
+            Self::Eq => "=",
+            Self::Ge => ">=",
+            Self::Gt => ">>",
+        }
+    }
+}
+
+impl Display for VersionOp {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for VersionOp {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "<<" => Ok(Self::Lt),
+            // `<` is deprecated and means `<=`.
+            "<=" | "<" => Ok(Self::Le),
+            "=" => Ok(Self::Eq),
+            // `>` is deprecated and means `>=`.
+            ">=" | ">" => Ok(Self::Ge),
+            ">>" => Ok(Self::Gt),
+            other => Err(Error::Dependency(other.into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::Arbitrary;
+    use arbitrary::Unstructured;
+    use arbtest::arbtest;
+
+    use super::*;
+
+    #[test]
+    fn display_parse() {
+        arbtest(|u| {
+            let expected: Dependencies = u.arbitrary()?;
+            let string = expected.to_string();
+            let actual: Dependencies = string
+                .parse()
+                .unwrap_or_else(|e| panic!("string = {:?}, error = {}", string, e));
+            assert_eq!(expected, actual, "string = {:?}", string);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn parse_real_dependencies() {
+        let deps: Dependencies = "libc6 (>= 2.34), python3:any, libfoo1 [!hurd-any] <!nocheck>, \
+                                  bar | baz:amd64 (<< 2.0) [linux-any any-i386]"
+            .parse()
+            .unwrap();
+        assert_eq!(4, deps.0.len());
+        assert_eq!(Some(ArchQualifier::Any), deps.0[1].0[0].arch);
+        assert_eq!(
+            "libc6 (>= 2.34), python3:any, libfoo1 [!hurd-any], \
+             bar | baz:amd64 (<< 2.0) [linux-any any-i386]",
+            deps.to_string()
+        );
+        assert!("foo [linux-any !hurd-any]".parse::<Dependency>().is_err());
+        assert!("foo (>= 1.0".parse::<Dependency>().is_err());
+        assert!("foo bar".parse::<Dependency>().is_err());
+    }
+
+    #[test]
+    fn arch_restrictions() {
+        let amd64: Arch = "amd64".parse().unwrap();
+        let hurd: Arch = "hurd-i386".parse().unwrap();
+        let deps: Dependencies = "foo [linux-any], bar [!linux-any], baz | qux [hurd-any]"
+            .parse()
+            .unwrap();
+        let enabled = |host: &Arch| -> Vec<String> {
+            deps.enabled_on(host)
+                .map(|choice| choice.to_string())
+                .collect()
+        };
+        assert_eq!(vec!["foo [linux-any]", "baz"], enabled(&amd64));
+        assert_eq!(
+            vec!["bar [!linux-any]", "baz | qux [hurd-any]"],
+            enabled(&hurd)
+        );
+    }
+
+    #[test]
+    fn multi_arch() {
+        let amd64: Arch = "amd64".parse().unwrap();
+        let package = |arch: &str, multi_arch: &str| -> Package {
+            let mut control = format!(
+                "Package: foo\nVersion: 1.0\nLicense: MIT\nArchitecture: {}\n\
+                 Maintainer: none\nDescription: foo\n",
+                arch
+            );
+            if !multi_arch.is_empty() {
+                control.push_str(&format!("Multi-Arch: {}\n", multi_arch));
+            }
+            control.parse().unwrap()
+        };
+        let matches = |dep: &str, package: &Package| {
+            dep.parse::<Dependency>().unwrap().matches(package, &amd64)
+        };
+        let native = package("amd64", "");
+        let all = package("all", "");
+        let foreign = package("arm64", "foreign");
+        let allowed = package("arm64", "allowed");
+        let same = package("arm64", "same");
+        assert!(matches("foo", &native));
+        assert!(matches("foo", &all));
+        assert!(matches("foo", &foreign));
+        assert!(!matches("foo", &allowed));
+        assert!(!matches("foo", &same));
+        assert!(!matches("foo:any", &native));
+        assert!(matches("foo:any", &foreign));
+        assert!(matches("foo:any", &allowed));
+        assert!(matches("foo:native", &native));
+        assert!(!matches("foo:native", &foreign));
+        assert!(matches("foo:arm64", &same));
+        assert!(!matches("foo:arm64", &native));
+        assert!(matches("foo (>= 1.0)", &native));
+        assert!(!matches("foo (>> 1.0)", &native));
+        assert!(!matches("bar", &native));
+        let choice: DependencyChoice = "bar | foo:any | foo".parse().unwrap();
+        let packages = [same.clone(), allowed.clone()];
+        assert_eq!(Some(&allowed), choice.find(&packages, &amd64));
+    }
+
+    #[test]
+    fn version_range_intersection() {
+        arbtest(|u| {
+            let constraints: Vec<VersionConstraint> = u.arbitrary()?;
+            let version: PackageVersion = u.arbitrary()?;
+            let range = constraints
+                .iter()
+                .map(VersionRange::from)
+                .fold(VersionRange::any(), |a, b| a.intersect(&b));
+            let expected = constraints.iter().all(|c| c.matches(&version));
+            assert_eq!(expected, range.contains(&version), "range = {}", range);
+            if range.is_empty() {
+                assert!(!range.contains(&version));
+            }
+            for c in constraints.iter() {
+                assert_eq!(
+                    c.matches(&version),
+                    VersionRange::from(c).contains(&version)
+                );
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn version_range_display() {
+        let range = |s: &str| -> VersionRange {
+            s.split(',')
+                .map(|c| VersionRange::from(&c.parse::<VersionConstraint>().unwrap()))
+                .fold(VersionRange::any(), |a, b| a.intersect(&b))
+        };
+        assert_eq!(">= 1.0, << 2.0", range(">= 1.0, << 2.0").to_string());
+        assert_eq!(
+            ">> 1.5, << 2.0",
+            range(">= 1.0, >> 1.5, << 2.0").to_string()
+        );
+        assert_eq!("= 1.0", range(">= 1.0, <= 1.0").to_string());
+        assert_eq!("none", range(">= 1.0, << 1.0").to_string());
+        assert_eq!("none", range("= 1.0, = 2.0").to_string());
+        assert_eq!("any", VersionRange::any().to_string());
+    }
+
+    impl<'a> Arbitrary<'a> for Dependencies {
+        fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+            let len = u.int_in_range(0..=5)?;
+            let mut choices = Vec::with_capacity(len);
+            for _ in 0..len {
+                let len = u.int_in_range(1..=3)?;
+                let mut alternatives = Vec::with_capacity(len);
+                for _ in 0..len {
+                    alternatives.push(u.arbitrary()?);
+                }
+                choices.push(DependencyChoice(alternatives));
+            }
+            Ok(Self(choices))
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for Dependency {
+        fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+            let negated: bool = u.arbitrary()?;
+            let len = u.int_in_range(0..=3)?;
+            let mut arch_restrictions = Vec::with_capacity(len);
+            for _ in 0..len {
+                arch_restrictions.push(ArchRestriction {
+                    negated,
+                    pattern: u.arbitrary()?,
+                });
+            }
+            Ok(Self {
+                name: u.arbitrary()?,
+                arch: u.arbitrary()?,
+                version: u.arbitrary()?,
+                arch_restrictions,
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for ArchQualifier {
+        fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+            Ok(match u.int_in_range(0..=2)? {
+                0 => Self::Any,
+                1 => Self::Native,
+                _ => Self::Arch(u.arbitrary()?),
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for VersionConstraint {
+        fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+            Ok(Self {
+                op: u.arbitrary()?,
+                version: u.arbitrary()?,
+            })
+        }
+    }
+}
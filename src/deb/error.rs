@@ -2,12 +2,19 @@ use std::borrow::Cow;
 
 use thiserror::Error;
 
+use crate::deb::Conflict;
+use crate::deb::PolicyViolations;
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("invalid package name {0:?}")]
     PackageName(String),
     #[error("invalid package version {0:?}")]
     PackageVersion(String),
+    #[error("invalid architecture {0:?}")]
+    Arch(String),
+    #[error("invalid dependency {0:?}")]
+    Dependency(String),
     #[error("invalid field name {0:?}")]
     FieldName(String),
     #[error("invalid field value {0:?}")]
@@ -28,6 +35,10 @@ pub enum Error {
     Md5Sums,
     #[error("invalid md5 hash")]
     InvalidMd5,
+    #[error("repository policy violations:\n{0}")]
+    Policy(PolicyViolations),
+    #[error("{0}")]
+    Conflict(Box<Conflict>),
     #[error("{0}")]
     Other(String),
 }
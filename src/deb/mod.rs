@@ -1,4 +1,6 @@
+mod arch;
 mod constants;
+mod dependency;
 mod error;
 mod field_name;
 mod folded_value;
@@ -7,13 +9,17 @@ mod multiline_value;
 mod package;
 mod package_name;
 mod package_version;
+mod policy;
 mod release;
 mod repository;
+mod resolver;
 mod signer;
 mod simple_value;
 mod value;
 
+pub use self::arch::*;
 pub use self::constants::*;
+pub use self::dependency::*;
 pub use self::error::*;
 pub use self::field_name::*;
 pub use self::folded_value::*;
@@ -22,8 +28,10 @@ pub use self::multiline_value::*;
 pub use self::package::*;
 pub use self::package_name::*;
 pub use self::package_version::*;
+pub use self::policy::*;
 pub use self::release::*;
 pub use self::repository::*;
+pub use self::resolver::*;
 pub use self::signer::*;
 pub use self::simple_value::*;
 pub use self::value::*;
@@ -12,9 +12,15 @@ use normalize_path::NormalizePath;
 
 use crate::archive::ArchiveRead;
 use crate::archive::ArchiveWrite;
+use crate::archive::ModePolicy;
+use crate::artifact::ArtifactFormat;
+use crate::artifact::ArtifactName;
 use crate::compress::AnyDecoder;
+use crate::deb::Arch;
+use crate::deb::Dependencies;
 use crate::deb::Error;
 use crate::deb::FieldName;
+use crate::deb::MultiArch;
 use crate::deb::MultilineValue;
 use crate::deb::PackageName;
 use crate::deb::PackageSigner;
@@ -45,13 +51,57 @@ impl Package {
         &self.name
     }
 
+    pub fn arch(&self) -> Result<Arch, Error> {
+        self.architecture.as_str().parse()
+    }
+
+    pub fn multi_arch(&self) -> Result<MultiArch, Error> {
+        match self.other.get("multi-arch") {
+            Some(value) => value.as_str().parse(),
+            None => Ok(Default::default()),
+        }
+    }
+
+    pub fn depends(&self) -> Result<Dependencies, Error> {
+        match self.other.get("depends") {
+            Some(value) => value.as_str().parse(),
+            None => Ok(Default::default()),
+        }
+    }
+
+    pub fn artifact_name(&self) -> ArtifactName {
+        ArtifactName {
+            name: self.name.to_string(),
+            version: self.version.to_string_without_epoch(),
+            arch: Some(self.architecture.to_string()),
+            format: ArtifactFormat::Deb,
+        }
+    }
+
+    /// Conventional package file name: `<name>_<version>_<arch>.deb`.
+    pub fn file_name(&self) -> String {
+        self.artifact_name().to_string()
+    }
+
     pub fn write<W: Write, P: AsRef<Path>>(
         &self,
         directory: P,
         writer: W,
         signer: &PackageSigner,
     ) -> Result<(), std::io::Error> {
-        let data = TarGz::from_directory(directory, gz_writer())?.finish()?;
+        self.write_with_mode_policy(directory, writer, signer, &Default::default())
+    }
+
+    /// Write the package normalizing file modes according to `mode_policy`.
+    pub fn write_with_mode_policy<W: Write, P: AsRef<Path>>(
+        &self,
+        directory: P,
+        writer: W,
+        signer: &PackageSigner,
+        mode_policy: &ModePolicy,
+    ) -> Result<(), std::io::Error> {
+        let data = TarGz::from_directory_with_mode_policy(directory, mode_policy, gz_writer())?
+            .finish()?;
         let control = TarGz::from_files([("control", self.to_string())], gz_writer())?.finish()?;
         let mut message_bytes: Vec<u8> = Vec::new();
         message_bytes.extend(DEBIAN_BINARY_CONTENTS.as_bytes());
@@ -268,6 +318,14 @@ impl Fields {
             .ok_or_else(|| Error::MissingField(name))
     }
 
+    pub fn get(&self, name: &'static str) -> Option<&Value> {
+        self.fields.get(&FieldName::new_unchecked(name))
+    }
+
+    pub fn contains(&self, name: &FieldName) -> bool {
+        self.fields.contains_key(name)
+    }
+
     pub fn clear(&mut self) {
         self.fields.clear();
     }
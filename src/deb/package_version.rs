@@ -27,6 +27,16 @@ impl PackageVersion {
         Self::do_new(version).map_err(|version| Error::PackageVersion(version.to_string()))
     }
 
+    /// Version string without the epoch as used in the package file names.
+    pub fn to_string_without_epoch(&self) -> String {
+        let mut s = self.upstream_version.0.clone();
+        if !self.debian_revision.0.is_empty() {
+            s.push('-');
+            s.push_str(&self.debian_revision.0);
+        }
+        s
+    }
+
     fn do_new(version: &str) -> Result<Self, &str> {
         let (epoch, version) = match version.find(|ch| ch == ':') {
             Some(i) => (
@@ -0,0 +1,329 @@
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::deb::Error;
+use crate::deb::FieldName;
+use crate::deb::Package;
+use crate::deb::PackageVerifier;
+use crate::hash::MultiHashReader;
+
+/// Repository admission policy.
+///
+/// The policy is stored in JSON format:
+///
+/// ```json
+/// {
+///   "allowed-licenses": ["MIT", "Apache-2.0", "GPL-2.0-only WITH Classpath-exception-2.0"],
+///   "required-fields": ["Homepage", "Section"],
+///   "max-size": 104857600
+/// }
+/// ```
+///
+/// All the keys are optional. Default policy admits any package.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Policy {
+    /// SPDX license identifiers. Empty list means that any license is allowed.
+    #[serde(default)]
+    pub allowed_licenses: Vec<String>,
+    /// Control fields that every package must have.
+    #[serde(default)]
+    pub required_fields: Vec<String>,
+    /// Maximum package file size in bytes.
+    #[serde(default)]
+    pub max_size: Option<u64>,
+}
+
+impl Policy {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        serde_json::from_reader(file)
+            .map_err(|e| Error::other(format!("failed to read {}: {}", path.display(), e)))
+    }
+
+    /// Check the package file without adding it to the repository.
+    pub fn admit<P: AsRef<Path>>(&self, path: P, verifier: &PackageVerifier) -> Result<(), Error> {
+        let path = path.as_ref();
+        let mut reader = MultiHashReader::new(File::open(path)?);
+        let control = Package::read_control(reader.by_ref(), verifier)?;
+        let (_hash, size) = reader.digest()?;
+        self.check(&control, size as u64).map_err(|violations| {
+            Error::Policy(PolicyViolations(vec![(path.to_path_buf(), violations)]))
+        })
+    }
+
+    /// Check package metadata and the size of the package file.
+    pub fn check(&self, package: &Package, size: u64) -> Result<(), Vec<PolicyViolation>> {
+        let mut violations = Vec::new();
+        if !self.allowed_licenses.is_empty() {
+            match is_license_allowed(package.license.as_str(), &self.allowed_licenses) {
+                Some(true) => {}
+                Some(false) => violations.push(PolicyViolation::License(
+                    package.license.as_str().to_string(),
+                )),
+                None => violations.push(PolicyViolation::InvalidLicense(
+                    package.license.as_str().to_string(),
+                )),
+            }
+        }
+        for name in self.required_fields.iter() {
+            if !has_field(package, name) {
+                violations.push(PolicyViolation::MissingField(name.clone()));
+            }
+        }
+        if let Some(max_size) = self.max_size {
+            if size > max_size {
+                violations.push(PolicyViolation::Size { size, max_size });
+            }
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyViolation {
+    License(String),
+    InvalidLicense(String),
+    MissingField(String),
+    Size { size: u64, max_size: u64 },
+}
+
+impl Display for PolicyViolation {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::License(license) => write!(f, "license {:?} is not allowed", license),
+            Self::InvalidLicense(license) => {
+                write!(f, "license {:?} is not a valid SPDX expression", license)
+            }
+            Self::MissingField(name) => write!(f, "required field {:?} is missing", name),
+            Self::Size { size, max_size } => write!(
+                f,
+                "package size {} exceeds the limit of {} bytes",
+                size, max_size
+            ),
+        }
+    }
+}
+
+/// Policy violations of each rejected package file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyViolations(pub Vec<(PathBuf, Vec<PolicyViolation>)>);
+
+impl Display for PolicyViolations {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        for (i, (path, violations)) in self.0.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}:", path.display())?;
+            for violation in violations.iter() {
+                write!(f, "\n- {}", violation)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn has_field(package: &Package, name: &str) -> bool {
+    let Ok(name) = FieldName::try_from(name.to_string()) else {
+        return false;
+    };
+    let name = &name;
+    if name == "installed-size" {
+        return package.installed_size.is_some();
+    }
+    // These fields are always present.
+    ALWAYS_PRESENT_FIELDS.iter().any(|x| name == *x) || package.other.contains(name)
+}
+
+/// Evaluate SPDX license expression against the list of allowed licenses.
+///
+/// Returns `None` if the expression is malformed.
+fn is_license_allowed(expression: &str, allowed: &[String]) -> Option<bool> {
+    let mut parser = LicenseExpression {
+        tokens: tokenize(expression),
+        pos: 0,
+        allowed,
+    };
+    let result = parser.or()?;
+    if parser.pos != parser.tokens.len() {
+        return None;
+    }
+    Some(result)
+}
+
+struct LicenseExpression<'a> {
+    tokens: Vec<&'a str>,
+    pos: usize,
+    allowed: &'a [String],
+}
+
+impl<'a> LicenseExpression<'a> {
+    fn or(&mut self) -> Option<bool> {
+        let mut result = self.and()?;
+        while self.accept("OR") {
+            result |= self.and()?;
+        }
+        Some(result)
+    }
+
+    fn and(&mut self) -> Option<bool> {
+        let mut result = self.atom()?;
+        while self.accept("AND") {
+            result &= self.atom()?;
+        }
+        Some(result)
+    }
+
+    fn atom(&mut self) -> Option<bool> {
+        let token = self.next_token()?;
+        if token == "(" {
+            let result = self.or()?;
+            return (self.next_token()? == ")").then_some(result);
+        }
+        if !is_identifier(token) {
+            return None;
+        }
+        if self.accept("WITH") {
+            let exception = self.next_token()?;
+            if !is_identifier(exception) {
+                return None;
+            }
+            let license = format!("{} WITH {}", token, exception);
+            return Some(self.is_allowed(&license) || self.is_allowed(token));
+        }
+        Some(self.is_allowed(token))
+    }
+
+    fn is_allowed(&self, license: &str) -> bool {
+        self.allowed.iter().any(|x| x.eq_ignore_ascii_case(license))
+    }
+
+    fn accept(&mut self, operator: &str) -> bool {
+        match self.tokens.get(self.pos) {
+            Some(token) if token.eq_ignore_ascii_case(operator) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn next_token(&mut self) -> Option<&'a str> {
+        let token = self.tokens.get(self.pos).copied()?;
+        self.pos += 1;
+        Some(token)
+    }
+}
+
+fn is_identifier(token: &str) -> bool {
+    !(token == "("
+        || token == ")"
+        || ["AND", "OR", "WITH"]
+            .iter()
+            .any(|x| x.eq_ignore_ascii_case(token)))
+}
+
+fn tokenize(s: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, ch) in s.char_indices() {
+        if ch == '(' || ch == ')' || ch.is_whitespace() {
+            if let Some(j) = start.take() {
+                tokens.push(&s[j..i]);
+            }
+            if !ch.is_whitespace() {
+                tokens.push(&s[i..i + 1]);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(j) = start {
+        tokens.push(&s[j..]);
+    }
+    tokens
+}
+
+const ALWAYS_PRESENT_FIELDS: [&str; 6] = [
+    "package",
+    "version",
+    "license",
+    "architecture",
+    "maintainer",
+    "description",
+];
+
+#[cfg(test)]
+mod tests {
+    use arbtest::arbtest;
+
+    use super::*;
+
+    #[test]
+    fn license_expressions() {
+        let allowed = ["MIT".to_string(), "Apache-2.0".to_string()];
+        assert_eq!(Some(true), is_license_allowed("MIT", &allowed));
+        assert_eq!(Some(true), is_license_allowed("mit", &allowed));
+        assert_eq!(Some(false), is_license_allowed("GPL-3.0-only", &allowed));
+        assert_eq!(
+            Some(true),
+            is_license_allowed("MIT OR GPL-3.0-only", &allowed)
+        );
+        assert_eq!(
+            Some(false),
+            is_license_allowed("MIT AND GPL-3.0-only", &allowed)
+        );
+        assert_eq!(
+            Some(true),
+            is_license_allowed("(MIT OR GPL-3.0-only) AND Apache-2.0", &allowed)
+        );
+        assert_eq!(
+            Some(true),
+            is_license_allowed("Apache-2.0 WITH LLVM-exception", &allowed)
+        );
+        assert_eq!(None, is_license_allowed("MIT OR", &allowed));
+        assert_eq!(None, is_license_allowed("(MIT", &allowed));
+        assert_eq!(None, is_license_allowed("MIT)", &allowed));
+        assert_eq!(None, is_license_allowed("MIT Apache-2.0", &allowed));
+        assert_eq!(None, is_license_allowed("", &allowed));
+    }
+
+    #[test]
+    fn policy_check() {
+        arbtest(|u| {
+            let mut package: Package = u.arbitrary()?;
+            package.license = "GPL-3.0-only".parse().unwrap();
+            package.other.clear();
+            let policy: Policy = serde_json::from_str(
+                r#"{"allowed-licenses": ["MIT"], "required-fields": ["Homepage", "Package"], "max-size": 100}"#,
+            )
+            .unwrap();
+            assert_eq!(
+                Err(vec![
+                    PolicyViolation::License("GPL-3.0-only".into()),
+                    PolicyViolation::MissingField("Homepage".into()),
+                    PolicyViolation::Size {
+                        size: 101,
+                        max_size: 100
+                    },
+                ]),
+                policy.check(&package, 101)
+            );
+            assert_eq!(Ok(()), Policy::default().check(&package, 101));
+            Ok(())
+        });
+    }
+}
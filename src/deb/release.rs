@@ -1,9 +1,11 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::collections::HashSet;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Write;
+use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::time::SystemTime;
 
 use chrono::DateTime;
@@ -12,8 +14,10 @@ use chrono::Utc;
 use crate::deb::Error;
 use crate::deb::Repository;
 use crate::deb::SimpleValue;
-use crate::hash::MultiHash;
+use crate::hash::Md5Hash;
 use crate::hash::MultiHashReader;
+use crate::hash::Sha1Hash;
+use crate::hash::Sha256Hash;
 
 // https://wiki.debian.org/DebianRepository/Format#A.22Release.22_files
 pub struct Release {
@@ -22,7 +26,8 @@ pub struct Release {
     architectures: HashSet<SimpleValue>,
     components: HashSet<SimpleValue>,
     suite: SimpleValue,
-    checksums: HashMap<PathBuf, Checksums>,
+    acquire_by_hash: bool,
+    files: BTreeMap<PathBuf, IndexFile>,
 }
 
 impl Release {
@@ -32,19 +37,19 @@ impl Release {
         packages_str: &str,
     ) -> Result<Self, Error> {
         let architectures = packages.architectures();
-        let mut checksums = HashMap::new();
-        let reader = MultiHashReader::new(packages_str.as_bytes());
-        let (hash, size) = reader.digest()?;
-        checksums.insert("Packages".into(), Checksums { size, hash });
+        let mut files = BTreeMap::new();
+        let path: PathBuf = "Packages".into();
+        files.insert(path.clone(), IndexFile::new(path, packages_str)?);
         for (arch, per_arch_packages) in packages.iter() {
             let mut path = PathBuf::new();
             path.push("main");
             path.push(format!("binary-{}", arch));
             path.push("Packages");
             let per_arch_packages_string = per_arch_packages.to_string();
-            let reader = MultiHashReader::new(per_arch_packages_string.as_bytes());
-            let (hash, size) = reader.digest()?;
-            checksums.insert(path, Checksums { size, hash });
+            files.insert(
+                path.clone(),
+                IndexFile::new(path, per_arch_packages_string.as_str())?,
+            );
         }
         Ok(Self {
             date: SystemTime::now(),
@@ -52,9 +57,60 @@ impl Release {
             architectures,
             components: ["main".parse::<SimpleValue>()?].into(),
             suite,
-            checksums,
+            acquire_by_hash: false,
+            files,
         })
     }
+
+    pub fn date(&self) -> SystemTime {
+        self.date
+    }
+
+    pub fn valid_until(&self) -> Option<SystemTime> {
+        self.valid_until
+    }
+
+    pub fn architectures(&self) -> &HashSet<SimpleValue> {
+        &self.architectures
+    }
+
+    pub fn components(&self) -> &HashSet<SimpleValue> {
+        &self.components
+    }
+
+    pub fn suite(&self) -> &SimpleValue {
+        &self.suite
+    }
+
+    /// Whether the index files can be downloaded via [`IndexFile::by_hash_path`].
+    pub fn acquire_by_hash(&self) -> bool {
+        self.acquire_by_hash
+    }
+
+    /// All the files listed in the checksum sections.
+    pub fn files(&self) -> impl Iterator<Item = &IndexFile> {
+        self.files.values()
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&IndexFile> {
+        self.files.get(path)
+    }
+
+    /// Binary package indices (`Packages`, `Packages.gz` etc.) of the specified component and
+    /// architecture.
+    pub fn index_files(&self, component: &str, arch: &str) -> Vec<&IndexFile> {
+        let dir = Path::new(component).join(format!("binary-{}", arch));
+        self.files
+            .values()
+            .filter(|file| file.path.parent() == Some(dir.as_path()))
+            .filter(|file| {
+                file.path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name == "Packages" || name.starts_with("Packages."))
+            })
+            .collect()
+    }
 }
 
 impl Display for Release {
@@ -63,7 +119,7 @@ impl Display for Release {
         writeln!(f, "Date: {}", date.to_rfc2822())?;
         if let Some(valid_until) = self.valid_until {
             let valid_until: DateTime<Utc> = valid_until.into();
-            writeln!(f, "Valid-Until: {}", valid_until)?;
+            writeln!(f, "Valid-Until: {}", valid_until.to_rfc2822())?;
         }
         write!(f, "Architectures:")?;
         for arch in self.architectures.iter() {
@@ -76,31 +132,23 @@ impl Display for Release {
         }
         writeln!(f)?;
         writeln!(f, "Suite: {}", self.suite)?;
+        if self.acquire_by_hash {
+            writeln!(f, "Acquire-By-Hash: yes")?;
+        }
         let mut md5 = String::new();
         let mut sha1 = String::new();
         let mut sha256 = String::new();
-        for (path, sums) in self.checksums.iter() {
-            write!(
-                &mut md5,
-                "\n {:x} {} {}",
-                sums.hash.md5,
-                sums.size,
-                path.display()
-            )?;
-            write!(
-                &mut sha1,
-                "\n {} {} {}",
-                sums.hash.sha1,
-                sums.size,
-                path.display()
-            )?;
-            write!(
-                &mut sha256,
-                "\n {} {} {}",
-                sums.hash.sha2,
-                sums.size,
-                path.display()
-            )?;
+        for file in self.files.values() {
+            let path = file.path.display();
+            if let Some(hash) = file.hashes.md5.as_ref() {
+                write!(&mut md5, "\n {} {} {}", hash, file.size, path)?;
+            }
+            if let Some(hash) = file.hashes.sha1.as_ref() {
+                write!(&mut sha1, "\n {} {} {}", hash, file.size, path)?;
+            }
+            if let Some(hash) = file.hashes.sha256.as_ref() {
+                write!(&mut sha256, "\n {} {} {}", hash, file.size, path)?;
+            }
         }
         writeln!(f, "MD5Sum: {}", md5)?;
         writeln!(f, "SHA1: {}", sha1)?;
@@ -109,7 +157,275 @@ impl Display for Release {
     }
 }
 
-struct Checksums {
-    hash: MultiHash,
-    size: usize,
+impl FromStr for Release {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut fields: Vec<(String, String)> = Vec::new();
+        for line in value.lines() {
+            if line.starts_with([' ', '\t']) {
+                let (_, value) = fields
+                    .last_mut()
+                    .ok_or_else(|| Error::Package(line.into()))?;
+                value.push('\n');
+                value.push_str(line.trim());
+                continue;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| Error::Package(line.into()))?;
+            fields.push((name.to_lowercase(), value.trim().to_string()));
+        }
+        let mut date = None;
+        let mut valid_until = None;
+        let mut architectures = HashSet::new();
+        let mut components = HashSet::new();
+        let mut suite = None;
+        let mut codename = None;
+        let mut acquire_by_hash = false;
+        let mut files: BTreeMap<PathBuf, IndexFile> = BTreeMap::new();
+        for (name, value) in fields.into_iter() {
+            match name.as_str() {
+                "date" => date = Some(parse_date(&value)?),
+                "valid-until" => valid_until = Some(parse_date(&value)?),
+                "architectures" => architectures = parse_words(&value)?,
+                "components" => components = parse_words(&value)?,
+                "suite" => suite = Some(value.parse()?),
+                "codename" => codename = Some(value.parse()?),
+                "acquire-by-hash" => acquire_by_hash = value == "yes",
+                "md5sum" | "sha1" | "sha256" => {
+                    for line in value.lines().filter(|line| !line.is_empty()) {
+                        let mut iter = line.split_whitespace();
+                        let (Some(hash), Some(size), Some(path), None) =
+                            (iter.next(), iter.next(), iter.next(), iter.next())
+                        else {
+                            return Err(Error::FieldValue(line.into()));
+                        };
+                        let size: usize =
+                            size.parse().map_err(|_| Error::FieldValue(line.into()))?;
+                        let path: PathBuf = path.into();
+                        let file = files.entry(path.clone()).or_insert_with(|| IndexFile {
+                            path,
+                            size,
+                            hashes: Default::default(),
+                        });
+                        if file.size != size {
+                            return Err(Error::FieldValue(line.into()));
+                        }
+                        let invalid_hash = |_| Error::FieldValue(hash.into());
+                        match name.as_str() {
+                            "md5sum" => file.hashes.md5 = Some(hash.parse().map_err(invalid_hash)?),
+                            "sha1" => file.hashes.sha1 = Some(hash.parse().map_err(invalid_hash)?),
+                            _ => file.hashes.sha256 = Some(hash.parse().map_err(invalid_hash)?),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(Self {
+            date: date.ok_or(Error::MissingField("date"))?,
+            valid_until,
+            architectures,
+            components,
+            suite: suite.or(codename).ok_or(Error::MissingField("suite"))?,
+            acquire_by_hash,
+            files,
+        })
+    }
+}
+
+/// The file that is listed in the checksum sections of the release.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct IndexFile {
+    /// Path relative to the directory that contains the release file.
+    pub path: PathBuf,
+    pub size: usize,
+    pub hashes: IndexHashes,
+}
+
+impl IndexFile {
+    fn new(path: PathBuf, contents: &str) -> Result<Self, Error> {
+        let reader = MultiHashReader::new(contents.as_bytes());
+        let (hash, size) = reader.digest()?;
+        Ok(Self {
+            path,
+            size,
+            hashes: IndexHashes {
+                md5: Some(Md5Hash::new(hash.md5.0)),
+                sha1: Some(hash.sha1),
+                sha256: Some(hash.sha2),
+            },
+        })
+    }
+
+    /// `Acquire-By-Hash` path of the file, e.g. `main/binary-amd64/by-hash/SHA256/<hash>`.
+    ///
+    /// The strongest available hash is used.
+    pub fn by_hash_path(&self) -> Option<PathBuf> {
+        let (name, hash) = if let Some(hash) = self.hashes.sha256.as_ref() {
+            ("SHA256", hash.to_string())
+        } else if let Some(hash) = self.hashes.sha1.as_ref() {
+            ("SHA1", hash.to_string())
+        } else {
+            ("MD5Sum", self.hashes.md5.as_ref()?.to_string())
+        };
+        let mut path = self
+            .path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        path.push("by-hash");
+        path.push(name);
+        path.push(hash);
+        Some(path)
+    }
+}
+
+/// Hashes of the index file. Release files do not have to include all the checksum sections.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct IndexHashes {
+    pub md5: Option<Md5Hash>,
+    pub sha1: Option<Sha1Hash>,
+    pub sha256: Option<Sha256Hash>,
+}
+
+fn parse_date(value: &str) -> Result<SystemTime, Error> {
+    // `UTC` is not a valid RFC2822 time zone, but it is used in the wild.
+    let rfc2822 = match value.strip_suffix(" UTC") {
+        Some(prefix) => format!("{} +0000", prefix),
+        None => value.to_string(),
+    };
+    let date =
+        DateTime::parse_from_rfc2822(&rfc2822).map_err(|_| Error::FieldValue(value.into()))?;
+    Ok(date.to_utc().into())
+}
+
+fn parse_words(value: &str) -> Result<HashSet<SimpleValue>, Error> {
+    value.split_whitespace().map(|word| word.parse()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_release() {
+        let release: Release = RELEASE.parse().unwrap();
+        assert_eq!("stable", release.suite().to_string());
+        assert_eq!(3, release.components().len());
+        assert!(release.architectures().contains(&"amd64".parse().unwrap()));
+        assert!(release.acquire_by_hash());
+        let date: DateTime<Utc> = release.date().into();
+        assert_eq!("2024-06-29T09:32:06+00:00", date.to_rfc3339());
+        let index_files = release.index_files("main", "amd64");
+        let paths: Vec<&Path> = index_files.iter().map(|file| file.path.as_path()).collect();
+        assert_eq!(
+            vec![
+                Path::new("main/binary-amd64/Packages"),
+                Path::new("main/binary-amd64/Packages.gz")
+            ],
+            paths
+        );
+        let packages_gz = index_files[1];
+        assert_eq!(8959950, packages_gz.size);
+        assert_eq!(
+            Some(
+                "64d8ea1aedfdbcb19a6a4ab86dbf4c7c"
+                    .parse::<Md5Hash>()
+                    .unwrap()
+            ),
+            packages_gz.hashes.md5
+        );
+        assert_eq!(None, packages_gz.hashes.sha1);
+        assert_eq!(
+            Some(PathBuf::from(
+                "main/binary-amd64/by-hash/SHA256/2a8e3ae2bb16e0c4e0fd1a1ba16b4a6b4e1d05bc6a36fa4e1d89a9bde5b0a1e3"
+            )),
+            packages_gz.by_hash_path()
+        );
+        let release_file = release.get(Path::new("main/binary-amd64/Release")).unwrap();
+        assert_eq!(
+            Some(PathBuf::from(
+                "main/binary-amd64/by-hash/MD5Sum/0ae4b2c5a0e1a8e2b8d1d8a6a7d6a5a4"
+            )),
+            release_file.by_hash_path()
+        );
+        assert!(release.index_files("contrib", "arm64").is_empty());
+    }
+
+    #[test]
+    fn invalid_release() {
+        assert!(matches!(
+            "Suite: stable\n".parse::<Release>(),
+            Err(Error::MissingField("date"))
+        ));
+        assert!(matches!(
+            format!(
+                "{}MD5Sum:\n {} 1 Packages\nSHA256:\n {} 2 Packages\n",
+                HEADER,
+                "0".repeat(32),
+                "0".repeat(64)
+            )
+            .parse::<Release>(),
+            Err(Error::FieldValue(..))
+        ));
+        assert!(matches!(
+            format!("{}SHA256:\n xyz 1 Packages\n", HEADER).parse::<Release>(),
+            Err(Error::FieldValue(..))
+        ));
+    }
+
+    #[test]
+    fn display_parse() {
+        let release = Release {
+            date: SystemTime::UNIX_EPOCH,
+            valid_until: Some(SystemTime::UNIX_EPOCH),
+            architectures: ["amd64".parse().unwrap()].into(),
+            components: ["main".parse().unwrap()].into(),
+            suite: "stable".parse().unwrap(),
+            acquire_by_hash: true,
+            files: [
+                IndexFile::new("Packages".into(), "test").unwrap(),
+                IndexFile::new("main/binary-amd64/Packages".into(), "").unwrap(),
+            ]
+            .into_iter()
+            .map(|file| (file.path.clone(), file))
+            .collect(),
+        };
+        let actual: Release = release.to_string().parse().unwrap();
+        assert_eq!(release.date, actual.date);
+        assert_eq!(release.valid_until, actual.valid_until);
+        assert_eq!(release.architectures, actual.architectures);
+        assert_eq!(release.components, actual.components);
+        assert_eq!(release.suite, actual.suite);
+        assert_eq!(release.acquire_by_hash, actual.acquire_by_hash);
+        assert_eq!(release.files, actual.files);
+    }
+
+    const HEADER: &str = "Date: Sat, 29 Jun 2024 09:32:06 UTC\nSuite: stable\n";
+
+    const RELEASE: &str = "Origin: Debian
+Label: Debian
+Suite: stable
+Version: 12.6
+Codename: bookworm
+Changelogs: https://metadata.ftp-master.debian.org/changelogs/@CHANGEPATH@_changelog
+Date: Sat, 29 Jun 2024 09:32:06 UTC
+Acquire-By-Hash: yes
+No-Support-for-Architecture-all: Packages
+Architectures: all amd64 arm64 armel armhf i386 mips64el mipsel ppc64el s390x
+Components: main contrib non-free-firmware
+Description: Debian 12.6 Released 29 June 2024
+MD5Sum:
+ 0ae4b2c5a0e1a8e2b8d1d8a6a7d6a5a4      117 main/binary-amd64/Release
+ a3e4e4b5dbb5f6c7a1a2b3c4d5e6f7a8 46213780 main/binary-amd64/Packages
+ 64d8ea1aedfdbcb19a6a4ab86dbf4c7c  8959950 main/binary-amd64/Packages.gz
+ f1e2d3c4b5a6978877665544332211ff   120124 main/i18n/Translation-en.bz2
+SHA256:
+ 6f0aa2fd55c8a4fc69e8df5c4d2a1a5ec6bfb4c9e3c4ac7d0e8c3a9de8a2b8c1 46213780 main/binary-amd64/Packages
+ 2a8e3ae2bb16e0c4e0fd1a1ba16b4a6b4e1d05bc6a36fa4e1d89a9bde5b0a1e3  8959950 main/binary-amd64/Packages.gz
+";
 }
@@ -14,10 +14,14 @@ use walkdir::WalkDir;
 use crate::deb::Error;
 use crate::deb::Package;
 use crate::deb::PackageVerifier;
+use crate::deb::Policy;
+use crate::deb::PolicyViolations;
 use crate::deb::Release;
 use crate::deb::SimpleValue;
 use crate::hash::MultiHash;
 use crate::hash::MultiHashReader;
+#[cfg(feature = "cosign")]
+use crate::sign::CosignSigner;
 use crate::sign::PgpCleartextSigner;
 
 pub struct Repository {
@@ -35,19 +39,66 @@ impl Repository {
         P: AsRef<Path>,
         P2: AsRef<Path>,
     {
-        let mut packages: HashMap<SimpleValue, PerArchPackages> = HashMap::new();
+        Self::with_policy(output_dir, paths, verifier, &Default::default())
+    }
+
+    /// Build the repository rejecting the packages that violate the policy.
+    ///
+    /// All the packages are checked before any of them are moved to the output directory. The
+    /// returned error lists the violations of every rejected package.
+    pub fn with_policy<I, P, P2>(
+        output_dir: P2,
+        paths: I,
+        verifier: &PackageVerifier,
+        policy: &Policy,
+    ) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        let mut admitted = Vec::new();
+        let mut violations = Vec::new();
         let mut push_package = |path: &Path| -> Result<(), Error> {
-            eprintln!("reading {}", path.display());
+            log::info!("reading {}", path.display());
             let mut reader = MultiHashReader::new(File::open(path)?);
             let control = Package::read_control(reader.by_ref(), verifier)?;
+            control.artifact_name().check_path(path);
             let (hash, size) = reader.digest()?;
+            match policy.check(&control, size as u64) {
+                Ok(()) => admitted.push((path.to_path_buf(), control, hash, size)),
+                Err(v) => violations.push((path.to_path_buf(), v)),
+            }
+            Ok(())
+        };
+        for path in paths.into_iter() {
+            let path = path.as_ref();
+            if path.is_dir() {
+                for entry in WalkDir::new(path).sort_by_file_name().into_iter() {
+                    let entry = entry?;
+                    if entry.file_type().is_dir()
+                        || entry.path().extension() != Some(OsStr::new("deb"))
+                    {
+                        continue;
+                    }
+                    push_package(entry.path())?
+                }
+            } else {
+                push_package(path)?
+            }
+        }
+        if !violations.is_empty() {
+            return Err(Error::Policy(PolicyViolations(violations)));
+        }
+        let mut packages: HashMap<SimpleValue, PerArchPackages> = HashMap::new();
+        for (path, control, hash, size) in admitted.into_iter() {
             let mut filename = PathBuf::new();
             filename.push("data");
             filename.push(hash.sha2.to_string());
             create_dir_all(output_dir.as_ref().join(&filename))?;
             filename.push(path.file_name().unwrap());
             let new_path = output_dir.as_ref().join(&filename);
-            std::fs::rename(path, new_path)?;
+            std::fs::rename(&path, new_path)?;
             let control = ExtendedControlData {
                 control,
                 size,
@@ -61,23 +112,6 @@ impl Repository {
                 })
                 .packages
                 .push(control);
-            Ok(())
-        };
-        for path in paths.into_iter() {
-            let path = path.as_ref();
-            if path.is_dir() {
-                for entry in WalkDir::new(path).into_iter() {
-                    let entry = entry?;
-                    if entry.file_type().is_dir()
-                        || entry.path().extension() != Some(OsStr::new("deb"))
-                    {
-                        continue;
-                    }
-                    push_package(entry.path())?
-                }
-            } else {
-                push_package(path)?
-            }
         }
         Ok(Self { packages })
     }
@@ -118,6 +152,33 @@ impl Repository {
         Ok(())
     }
 
+    /// Write the repository and additionally sign `Release` file and the packages with cosign
+    /// key.
+    ///
+    /// The signatures are written to `Release.sig` and `<package>.deb.sig`.
+    #[cfg(feature = "cosign")]
+    pub fn write_with_cosign<P>(
+        &self,
+        output_dir: P,
+        suite: SimpleValue,
+        signer: &PgpCleartextSigner,
+        cosign_signer: &CosignSigner,
+    ) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let output_dir = output_dir.as_ref();
+        let release_file = output_dir.join(suite.to_string()).join("Release");
+        self.write(output_dir, suite, signer)?;
+        cosign_signer.sign_file(release_file)?;
+        for per_arch_packages in self.packages.values() {
+            for control in per_arch_packages.packages.iter() {
+                cosign_signer.sign_file(output_dir.join(&control.filename))?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&SimpleValue, &PerArchPackages)> {
         self.packages.iter()
     }
@@ -183,6 +244,97 @@ mod tests {
     use crate::test::DirectoryOfFiles;
     use crate::test::UpperHex;
 
+    #[test]
+    fn policy_violations_of_all_packages() {
+        let (signing_key, verifying_key) = SigningKey::generate("wolfpack-pgp-id".into()).unwrap();
+        let signer = PackageSigner::new(signing_key);
+        let verifier = PackageVerifier::new(verifying_key);
+        let policy: Policy = serde_json::from_str(r#"{"allowed-licenses": ["MIT"]}"#).unwrap();
+        arbtest(|u| {
+            let workdir = TempDir::new().unwrap();
+            let root = workdir.path().join("root");
+            let mut deb_paths = Vec::new();
+            for (i, license) in ["GPL-3.0-only", "MIT", "Apache-2.0"].iter().enumerate() {
+                let mut control: Package = u.arbitrary()?;
+                control.license = license.parse().unwrap();
+                let directory: DirectoryOfFiles = u.arbitrary()?;
+                let deb_path = workdir.path().join(format!("{}.deb", i));
+                control
+                    .write(
+                        directory.path(),
+                        File::create(deb_path.as_path()).unwrap(),
+                        &signer,
+                    )
+                    .unwrap();
+                deb_paths.push(deb_path);
+            }
+            let Err(Error::Policy(violations)) =
+                Repository::with_policy(root.as_path(), deb_paths.iter(), &verifier, &policy)
+            else {
+                panic!("both packages with disallowed licenses should be rejected");
+            };
+            assert_eq!(
+                PolicyViolations(vec![
+                    (
+                        deb_paths[0].clone(),
+                        vec![PolicyViolation::License("GPL-3.0-only".into())]
+                    ),
+                    (
+                        deb_paths[2].clone(),
+                        vec![PolicyViolation::License("Apache-2.0".into())]
+                    ),
+                ]),
+                violations
+            );
+            // Admitted packages are not moved when some other packages are rejected.
+            assert!(deb_paths.iter().all(|path| path.exists()));
+            Ok(())
+        });
+    }
+
+    #[cfg(feature = "cosign")]
+    #[test]
+    fn cosign_release() {
+        let (signing_key, verifying_key) = SigningKey::generate("wolfpack-pgp-id".into()).unwrap();
+        let signer = PackageSigner::new(signing_key.clone());
+        let verifier = PackageVerifier::new(verifying_key);
+        let release_signer = PgpCleartextSigner::new(signing_key.into());
+        let (cosign_signer, cosign_verifier) = CosignSigner::generate();
+        arbtest(|u| {
+            let workdir = TempDir::new().unwrap();
+            let root = workdir.path().join("root");
+            let control: Package = u.arbitrary()?;
+            let directory: DirectoryOfFiles = u.arbitrary()?;
+            let deb_path = workdir.path().join("test.deb");
+            control
+                .write(
+                    directory.path(),
+                    File::create(deb_path.as_path()).unwrap(),
+                    &signer,
+                )
+                .unwrap();
+            let suite: SimpleValue = "meta".parse().unwrap();
+            Repository::new(root.as_path(), [deb_path.as_path()], &verifier)
+                .unwrap()
+                .write_with_cosign(root.as_path(), suite, &release_signer, &cosign_signer)
+                .unwrap();
+            let release_file = root.join("meta/Release");
+            assert!(root.join("meta/Release.sig").exists());
+            cosign_verifier.verify_file(&release_file).unwrap();
+            let mut release = std::fs::read(&release_file).unwrap();
+            release.push(b'\n');
+            std::fs::write(&release_file, release).unwrap();
+            assert!(cosign_verifier.verify_file(&release_file).is_err());
+            for entry in WalkDir::new(root.join("data")).into_iter() {
+                let entry = entry.unwrap();
+                if entry.path().extension() == Some(OsStr::new("deb")) {
+                    cosign_verifier.verify_file(entry.path()).unwrap();
+                }
+            }
+            Ok(())
+        });
+    }
+
     #[ignore]
     #[test]
     fn apt_adds_random_repositories() {
@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use serde::Serialize;
+
+use crate::deb::Arch;
+use crate::deb::Dependencies;
+use crate::deb::Dependency;
+use crate::deb::DependencyChoice;
+use crate::deb::Error;
+use crate::deb::Package;
+use crate::deb::PackageName;
+use crate::deb::VersionRange;
+
+/// Dependency resolver that picks the newest version of each package that satisfies all the
+/// constraints collected so far.
+///
+/// Before selecting a package the resolver intersects the version constraints of the pending
+/// dependencies on the same package. The resolver does not backtrack: once the package is
+/// selected, it stays selected.
+pub struct Resolver<'a> {
+    packages: &'a [Package],
+    host: Arch,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(packages: &'a [Package], host: Arch) -> Self {
+        Self { packages, host }
+    }
+
+    /// Resolve `dependencies` and their transitive dependencies.
+    ///
+    /// Returns the selected packages in the order of selection or [`Error::Conflict`] that
+    /// explains which packages required which conflicting version ranges.
+    pub fn resolve(&self, dependencies: &Dependencies) -> Result<Vec<&'a Package>, Error> {
+        let mut queue: VecDeque<(Option<&'a Package>, DependencyChoice)> = dependencies
+            .enabled_on(&self.host)
+            .map(|choice| (None, choice))
+            .collect();
+        let mut requirements: Requirements = HashMap::new();
+        let mut selected: HashMap<PackageName, &'a Package> = HashMap::new();
+        // The requirements that caused the packages to be selected.
+        let mut reasons: HashMap<PackageName, Requirement> = HashMap::new();
+        let mut packages = Vec::new();
+        while let Some((required_by, choice)) = queue.pop_front() {
+            let satisfied = choice.0.iter().find(|dep| {
+                selected
+                    .get(&dep.name)
+                    .is_some_and(|package| dep.matches(package, &self.host))
+            });
+            if let Some(dep) = satisfied {
+                requirements
+                    .entry(dep.name.clone())
+                    .or_default()
+                    .push((required_by, dep.clone()));
+                continue;
+            }
+            let Some((dep, package)) = choice
+                .0
+                .iter()
+                .filter(|dep| !selected.contains_key(&dep.name))
+                .find_map(|dep| {
+                    let pending = pending_range(&queue, &dep.name);
+                    Some((dep, self.find(dep, &requirements, &pending)?))
+                })
+            else {
+                let conflict =
+                    self.explain(required_by, &choice, &requirements, &selected, &reasons);
+                return Err(Error::Conflict(Box::new(conflict)));
+            };
+            let depends = package.depends()?;
+            queue.extend(
+                depends
+                    .enabled_on(&self.host)
+                    .map(|choice| (Some(package), choice)),
+            );
+            reasons.insert(
+                dep.name.clone(),
+                Requirement::new(required_by, dep, &reasons),
+            );
+            requirements
+                .entry(dep.name.clone())
+                .or_default()
+                .push((required_by, dep.clone()));
+            selected.insert(dep.name.clone(), package);
+            packages.push(package);
+        }
+        Ok(packages)
+    }
+
+    /// Find the newest package that satisfies `dep` and all the previous requirements.
+    ///
+    /// The packages that are also within the `pending` range are preferred.
+    fn find(
+        &self,
+        dep: &Dependency,
+        requirements: &Requirements,
+        pending: &VersionRange,
+    ) -> Option<&'a Package> {
+        let previous = requirements
+            .get(&dep.name)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let candidates: Vec<&'a Package> = self
+            .packages
+            .iter()
+            .filter(|package| {
+                dep.matches(package, &self.host)
+                    && previous
+                        .iter()
+                        .all(|(_, other)| other.matches(package, &self.host))
+            })
+            .collect();
+        candidates
+            .iter()
+            .filter(|package| pending.contains(&package.version))
+            .max_by(|a, b| a.version.cmp(&b.version))
+            .or_else(|| candidates.iter().max_by(|a, b| a.version.cmp(&b.version)))
+            .copied()
+    }
+
+    fn explain(
+        &self,
+        required_by: Option<&'a Package>,
+        choice: &DependencyChoice,
+        requirements: &Requirements,
+        selected: &HashMap<PackageName, &'a Package>,
+        reasons: &HashMap<PackageName, Requirement>,
+    ) -> Conflict {
+        let alternatives = choice
+            .0
+            .iter()
+            .map(|dep| {
+                let previous = requirements
+                    .get(&dep.name)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                let mut reqs: Vec<Requirement> = previous
+                    .iter()
+                    .map(|(required_by, dep)| Requirement::new(*required_by, dep, reasons))
+                    .collect();
+                reqs.push(Requirement::new(required_by, dep, reasons));
+                let range = previous
+                    .iter()
+                    .map(|(_, dep)| dep)
+                    .chain(std::iter::once(dep))
+                    .filter_map(|dep| dep.version.as_ref())
+                    .map(VersionRange::from)
+                    .fold(VersionRange::any(), |a, b| a.intersect(&b));
+                let available = self
+                    .packages
+                    .iter()
+                    .filter(|package| package.name == dep.name)
+                    .map(|package| format!("{} ({})", package.version, package.architecture))
+                    .collect();
+                Explanation {
+                    package: dep.name.to_string(),
+                    range: range.to_string(),
+                    requirements: reqs,
+                    available,
+                    selected: selected
+                        .get(&dep.name)
+                        .map(|package| package.version.to_string()),
+                }
+            })
+            .collect();
+        Conflict {
+            dependency: choice.to_string(),
+            required_by: required_by.map(package_id),
+            alternatives,
+        }
+    }
+}
+
+type Requirements<'a> = HashMap<PackageName, Vec<(Option<&'a Package>, Dependency)>>;
+
+/// Intersection of the version ranges of the pending dependencies on the package `name`.
+///
+/// Only the dependencies without alternatives are taken into account.
+fn pending_range(
+    queue: &VecDeque<(Option<&Package>, DependencyChoice)>,
+    name: &PackageName,
+) -> VersionRange {
+    queue
+        .iter()
+        .filter_map(|(_, choice)| match choice.0.as_slice() {
+            [dep] if dep.name == *name => dep.version.as_ref(),
+            _ => None,
+        })
+        .map(VersionRange::from)
+        .fold(VersionRange::any(), |a, b| a.intersect(&b))
+}
+
+fn package_id(package: &Package) -> String {
+    format!("{} {}", package.name, package.version)
+}
+
+/// Explanation of why the dependency can not be satisfied.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Conflict {
+    /// Unsatisfied dependency including all the alternatives.
+    pub dependency: String,
+    /// The package that depends on it or `None` for the top-level dependencies.
+    pub required_by: Option<String>,
+    /// Why each of the alternatives can not be selected.
+    pub alternatives: Vec<Explanation>,
+}
+
+impl Conflict {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("always succeeds")
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self.required_by.as_ref() {
+            Some(required_by) => writeln!(
+                f,
+                "unable to satisfy `{}` required by `{}`",
+                self.dependency, required_by
+            )?,
+            None => writeln!(f, "unable to satisfy `{}`", self.dependency)?,
+        }
+        for alternative in self.alternatives.iter() {
+            write!(f, "{}", alternative)?;
+        }
+        if self.alternatives.iter().any(|x| x.selected.is_some()) {
+            writeln!(
+                f,
+                "note: no backtracking, already selected versions are not reconsidered"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Conflict {}
+
+/// Requirements and candidates of the single alternative.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Explanation {
+    pub package: String,
+    /// Intersection of all the version constraints.
+    pub range: String,
+    pub requirements: Vec<Requirement>,
+    /// Versions and architectures of the packages with the same name.
+    pub available: Vec<String>,
+    /// The version that has already been selected.
+    pub selected: Option<String>,
+}
+
+impl Display for Explanation {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        writeln!(f, "  {}: version range {}", self.package, self.range)?;
+        for requirement in self.requirements.iter() {
+            requirement.write_tree(f, 4)?;
+        }
+        if self.available.is_empty() {
+            writeln!(f, "    no packages available")?;
+        } else {
+            writeln!(f, "    available: {}", self.available.join(", "))?;
+        }
+        if let Some(selected) = self.selected.as_ref() {
+            writeln!(f, "    selected: {}", selected)?;
+        }
+        Ok(())
+    }
+}
+
+/// The dependency and the chain of requirements that led to it.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Requirement {
+    pub required_by: Option<String>,
+    pub dependency: String,
+    /// Why `required_by` package was selected.
+    pub reason: Option<Box<Requirement>>,
+}
+
+impl Requirement {
+    fn new(
+        required_by: Option<&Package>,
+        dependency: &Dependency,
+        reasons: &HashMap<PackageName, Requirement>,
+    ) -> Self {
+        Self {
+            required_by: required_by.map(package_id),
+            dependency: dependency.to_string(),
+            reason: required_by
+                .and_then(|package| reasons.get(&package.name))
+                .cloned()
+                .map(Box::new),
+        }
+    }
+
+    fn write_tree(&self, f: &mut Formatter, indent: usize) -> std::fmt::Result {
+        writeln!(f, "{:indent$}{}", "", self, indent = indent)?;
+        if let Some(reason) = self.reason.as_ref() {
+            reason.write_tree(f, indent + 2)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Requirement {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self.required_by.as_ref() {
+            Some(required_by) => write!(f, "{} depends on {}", required_by, self.dependency),
+            None => write!(f, "requested {}", self.dependency),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve() {
+        let packages = [
+            package("app", "1.0", "lib (>= 1.0), tool | other"),
+            package("lib", "1.0", ""),
+            package("lib", "1.5", ""),
+            package("lib", "2.0", ""),
+            package("other", "1.0", ""),
+        ];
+        let resolver = Resolver::new(&packages, "amd64".parse().unwrap());
+        let selected = resolver.resolve(&"app".parse().unwrap()).unwrap();
+        let selected: Vec<String> = selected
+            .into_iter()
+            .map(|package| format!("{} {}", package.name, package.version))
+            .collect();
+        assert_eq!(vec!["app 1.0", "lib 2.0", "other 1.0"], selected);
+        let selected = resolver
+            .resolve(&"lib (<< 2.0), app".parse().unwrap())
+            .unwrap();
+        assert_eq!("1.5", selected[0].version.to_string());
+        let selected = resolver
+            .resolve(&"app, lib (<< 2.0)".parse().unwrap())
+            .unwrap();
+        assert_eq!("1.5", selected[1].version.to_string());
+    }
+
+    #[test]
+    fn explain_conflict() {
+        let packages = [
+            package("app", "1.0", "lib (>= 2.0)"),
+            package("lib", "1.0", ""),
+            package("lib", "2.0", ""),
+        ];
+        let resolver = Resolver::new(&packages, "amd64".parse().unwrap());
+        let Err(Error::Conflict(conflict)) =
+            resolver.resolve(&"lib (<< 2.0), app".parse().unwrap())
+        else {
+            panic!("expected conflict");
+        };
+        assert_eq!(Some("app 1.0"), conflict.required_by.as_deref());
+        assert_eq!("lib (>= 2.0)", conflict.dependency);
+        let explanation = &conflict.alternatives[0];
+        assert_eq!("none", explanation.range);
+        assert_eq!(Some("1.0"), explanation.selected.as_deref());
+        assert_eq!(
+            "unable to satisfy `lib (>= 2.0)` required by `app 1.0`\n  lib: version range none\n    requested lib (<< 2.0)\n    app 1.0 depends on lib (>= 2.0)\n      requested app\n    available: 1.0 (amd64), 2.0 (amd64)\n    selected: 1.0\nnote: no backtracking, already selected versions are not reconsidered\n",
+            conflict.to_string()
+        );
+        let json: serde_json::Value = serde_json::from_str(&conflict.to_json()).unwrap();
+        assert_eq!("app 1.0", json["required-by"]);
+        assert_eq!(
+            "lib (<< 2.0)",
+            json["alternatives"][0]["requirements"][0]["dependency"]
+        );
+        assert_eq!(
+            "app",
+            json["alternatives"][0]["requirements"][1]["reason"]["dependency"]
+        );
+    }
+
+    #[test]
+    fn pending_constraints() {
+        let packages = [
+            package("app", "1.0", "lib (>= 1.0)"),
+            package("tool", "1.0", "lib (<< 2.0)"),
+            package("lib", "1.0", ""),
+            package("lib", "1.5", ""),
+            package("lib", "2.0", ""),
+        ];
+        let resolver = Resolver::new(&packages, "amd64".parse().unwrap());
+        for dependencies in ["app, tool", "tool, app"] {
+            let selected = resolver.resolve(&dependencies.parse().unwrap()).unwrap();
+            let lib = selected
+                .iter()
+                .find(|package| package.name == "lib".parse().unwrap());
+            assert_eq!(
+                Some("1.5".to_string()),
+                lib.map(|package| package.version.to_string()),
+                "dependencies = {}",
+                dependencies
+            );
+        }
+    }
+
+    #[test]
+    fn explain_transitive_conflict() {
+        let packages = [
+            package("app", "1.0", "mid"),
+            package("mid", "1.0", "lib (>= 2.0)"),
+            package("lib", "1.0", ""),
+            package("lib", "2.0", ""),
+        ];
+        let resolver = Resolver::new(&packages, "amd64".parse().unwrap());
+        let Err(Error::Conflict(conflict)) =
+            resolver.resolve(&"lib (<< 2.0), app".parse().unwrap())
+        else {
+            panic!("expected conflict");
+        };
+        assert_eq!(
+            "unable to satisfy `lib (>= 2.0)` required by `mid 1.0`\n  lib: version range none\n    requested lib (<< 2.0)\n    mid 1.0 depends on lib (>= 2.0)\n      app 1.0 depends on mid\n        requested app\n    available: 1.0 (amd64), 2.0 (amd64)\n    selected: 1.0\nnote: no backtracking, already selected versions are not reconsidered\n",
+            conflict.to_string()
+        );
+    }
+
+    #[test]
+    fn missing_package() {
+        let packages = [package("app", "1.0", "lib | lib2 (>= 1.0)")];
+        let resolver = Resolver::new(&packages, "amd64".parse().unwrap());
+        let Err(Error::Conflict(conflict)) = resolver.resolve(&"app".parse().unwrap()) else {
+            panic!("expected conflict");
+        };
+        assert_eq!(2, conflict.alternatives.len());
+        assert!(conflict.alternatives[0].available.is_empty());
+        assert_eq!(">= 1.0", conflict.alternatives[1].range);
+    }
+
+    fn package(name: &str, version: &str, depends: &str) -> Package {
+        let mut control = format!(
+            "Package: {}\nVersion: {}\nLicense: MIT\nArchitecture: amd64\n\
+             Maintainer: none\nDescription: {}\n",
+            name, version, name
+        );
+        if !depends.is_empty() {
+            control.push_str(&format!("Depends: {}\n", depends));
+        }
+        control.parse().unwrap()
+    }
+}
@@ -10,6 +10,8 @@ use pgp::SignedPublicKey;
 use pgp::SignedSecretKey;
 use rand::rngs::OsRng;
 
+use crate::sign::read_signing_key;
+use crate::sign::read_verifying_key;
 use crate::sign::Error;
 use crate::sign::PgpSigner;
 use crate::sign::PgpVerifier;
@@ -66,6 +68,11 @@ impl From<SigningKey> for SignedSecretKey {
 }
 
 impl SigningKey {
+    /// Read the key either in ASCII-armored or in binary OpenPGP format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, std::io::Error> {
+        read_signing_key(bytes).map(Self)
+    }
+
     pub fn generate(user_id: String) -> Result<(SigningKey, VerifyingKey), Error> {
         use pgp::crypto::aead::AeadAlgorithm::*;
         use pgp::crypto::hash::HashAlgorithm::*;
@@ -101,6 +108,11 @@ impl SigningKey {
 pub struct VerifyingKey(SignedPublicKey);
 
 impl VerifyingKey {
+    /// Read the key either in ASCII-armored or in binary OpenPGP format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, std::io::Error> {
+        read_verifying_key(bytes).map(Self)
+    }
+
     pub fn write_armored<W: Write>(&self, mut writer: W) -> Result<(), std::io::Error> {
         self.0
             .to_armored_writer(writer.by_ref(), Default::default())
@@ -1,9 +1,11 @@
 mod package;
 mod repository;
+mod scripts;
 mod signer;
 
 pub use self::package::*;
 pub use self::repository::*;
+pub use self::scripts::*;
 pub use self::signer::*;
 
 pub type Error = crate::deb::Error;
@@ -12,13 +12,17 @@ use flate2::write::GzEncoder;
 use flate2::Compression;
 use normalize_path::NormalizePath;
 
+use crate::archive::add_executable_file;
 use crate::archive::ArchiveRead;
 use crate::archive::ArchiveWrite;
+use crate::artifact::ArtifactFormat;
+use crate::artifact::ArtifactName;
 use crate::compress::AnyDecoder;
 use crate::deb;
 use crate::deb::DEBIAN_BINARY_CONTENTS;
 use crate::deb::DEBIAN_BINARY_FILE_NAME;
 use crate::ipk::Error;
+use crate::ipk::MaintainerScripts;
 use crate::ipk::PackageSigner;
 use crate::ipk::PackageVerifier;
 use crate::sign::SignatureWriter;
@@ -29,11 +33,34 @@ use crate::sign::VerifyingReader;
 pub struct Package(deb::Package);
 
 impl Package {
+    pub fn artifact_name(&self) -> ArtifactName {
+        ArtifactName {
+            format: ArtifactFormat::Ipk,
+            ..self.0.artifact_name()
+        }
+    }
+
+    /// Conventional package file name: `<name>_<version>_<arch>.ipk`.
+    pub fn file_name(&self) -> String {
+        self.artifact_name().to_string()
+    }
+
     pub fn write<P1: AsRef<Path>, P2: Into<PathBuf>>(
         &self,
         directory: P1,
         output_file: P2,
         signer: &PackageSigner,
+    ) -> Result<(), std::io::Error> {
+        self.write_with_scripts(directory, output_file, &Default::default(), signer)
+    }
+
+    /// Write the package including maintainer scripts.
+    pub fn write_with_scripts<P1: AsRef<Path>, P2: Into<PathBuf>>(
+        &self,
+        directory: P1,
+        output_file: P2,
+        scripts: &MaintainerScripts,
+        signer: &PackageSigner,
     ) -> Result<(), std::io::Error> {
         let output_file: PathBuf = output_file.into();
         let writer = File::create(output_file.as_path())?;
@@ -41,8 +68,14 @@ impl Package {
         let writer = SignatureWriter::new(writer, signer, signature_output_file);
         let writer = GzEncoder::new(writer, Compression::best());
         let data = tar::Builder::from_directory(directory, gz_writer())?.finish()?;
-        let control =
-            tar::Builder::from_files([("control", self.0.to_string())], gz_writer())?.finish()?;
+        let control = {
+            let mut control = tar::Builder::new(gz_writer());
+            control.add_regular_file("control", self.0.to_string())?;
+            for (kind, script) in scripts.iter() {
+                add_executable_file(&mut control, kind.file_name(), script.to_string())?;
+            }
+            control.into_inner()?.finish()?
+        };
         tar::Builder::from_files(
             [
                 (DEBIAN_BINARY_FILE_NAME, DEBIAN_BINARY_CONTENTS.as_bytes()),
@@ -172,6 +205,52 @@ mod tests {
         });
     }
 
+    #[test]
+    fn write_scripts() {
+        let workdir = TempDir::new().unwrap();
+        let signing_key = SigningKey::generate(Some("wolfpack".into()));
+        arbtest(|u| {
+            let control: Package = u.arbitrary()?;
+            let scripts: MaintainerScripts = u.arbitrary()?;
+            let directory: DirectoryOfFiles = u.arbitrary()?;
+            let file_path = workdir.path().join("test.ipk");
+            control
+                .write_with_scripts(
+                    directory.path(),
+                    file_path.as_path(),
+                    &scripts,
+                    &signing_key,
+                )
+                .unwrap();
+            let mut actual = Vec::new();
+            let mut reader = tar::Archive::new(GzDecoder::new(File::open(&file_path).unwrap()));
+            for entry in reader.entries().unwrap() {
+                let entry = entry.unwrap();
+                if entry.path().unwrap().normalize() != Path::new("control.tar.gz") {
+                    continue;
+                }
+                let mut control_tar = tar::Archive::new(GzDecoder::new(entry));
+                for entry in control_tar.entries().unwrap() {
+                    let mut entry = entry.unwrap();
+                    let path = entry.path().unwrap().normalize();
+                    if path == Path::new("control") {
+                        continue;
+                    }
+                    let mode = entry.header().mode().unwrap();
+                    let mut contents = String::new();
+                    entry.read_to_string(&mut contents).unwrap();
+                    actual.push((path, mode, contents));
+                }
+            }
+            let expected: Vec<_> = scripts
+                .iter()
+                .map(|(kind, script)| (PathBuf::from(kind.file_name()), 0o755, script.to_string()))
+                .collect();
+            assert_eq!(expected, actual);
+            Ok(())
+        });
+    }
+
     #[ignore]
     #[test]
     fn opkg_installs_random_packages() {
@@ -22,6 +22,8 @@ use crate::ipk::Package;
 use crate::ipk::PackageSigner;
 use crate::ipk::PackageVerifier;
 use crate::ipk::SimpleValue;
+#[cfg(feature = "cosign")]
+use crate::sign::CosignSigner;
 
 pub struct Repository {
     packages: HashMap<SimpleValue, PerArchPackages>,
@@ -40,9 +42,10 @@ impl Repository {
     {
         let mut packages: HashMap<SimpleValue, PerArchPackages> = HashMap::new();
         let mut push_package = |path: &Path| -> Result<(), Error> {
-            eprintln!("reading {}", path.display());
+            log::info!("reading {}", path.display());
             let mut reader = Sha256Reader::new(File::open(path)?);
             let control = Package::read_control(reader.by_ref(), path, verifier)?;
+            control.artifact_name().check_path(path);
             let (hash, size) = reader.digest()?;
             let mut filename = PathBuf::new();
             filename.push(hash.to_string());
@@ -68,7 +71,7 @@ impl Repository {
         for path in paths.into_iter() {
             let path = path.as_ref();
             if path.is_dir() {
-                for entry in WalkDir::new(path).into_iter() {
+                for entry in WalkDir::new(path).sort_by_file_name().into_iter() {
                     let entry = entry?;
                     if entry.file_type().is_dir()
                         || entry.path().extension() != Some(OsStr::new("deb"))
@@ -108,6 +111,28 @@ impl Repository {
         Ok(())
     }
 
+    /// Write the repository and additionally sign `Packages.gz` and the packages with cosign key.
+    ///
+    /// The signatures are written to `<file>.sig`. `Packages` is not signed with cosign because
+    /// `Packages.sig` is the signature that `opkg` verifies.
+    #[cfg(feature = "cosign")]
+    pub fn write_with_cosign<P: AsRef<Path>>(
+        &self,
+        output_dir: P,
+        signer: &PackageSigner,
+        cosign_signer: &CosignSigner,
+    ) -> Result<(), Error> {
+        let output_dir = output_dir.as_ref();
+        self.write(output_dir, signer)?;
+        cosign_signer.sign_file(output_dir.join("Packages.gz"))?;
+        for per_arch_packages in self.packages.values() {
+            for control in per_arch_packages.packages.iter() {
+                cosign_signer.sign_file(output_dir.join(&control.filename))?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&SimpleValue, &PerArchPackages)> {
         self.packages.iter()
     }
@@ -170,6 +195,44 @@ mod tests {
     use crate::ipk::SigningKey;
     use crate::test::DirectoryOfFiles;
 
+    #[cfg(feature = "cosign")]
+    #[test]
+    fn cosign_packages() {
+        let signing_key = SigningKey::generate(Some("wolfpack".into()));
+        let verifying_key = signing_key.to_verifying_key();
+        let (cosign_signer, cosign_verifier) = CosignSigner::generate();
+        arbtest(|u| {
+            let workdir = TempDir::new().unwrap();
+            let repo_dir = workdir.path().join("repo");
+            let package: Package = u.arbitrary()?;
+            let directory: DirectoryOfFiles = u.arbitrary()?;
+            let package_path = workdir.path().join("test.ipk");
+            package
+                .write(directory.path(), package_path.as_path(), &signing_key)
+                .unwrap();
+            let repository = Repository::new(&repo_dir, [&package_path], &verifying_key).unwrap();
+            repository
+                .write_with_cosign(&repo_dir, &signing_key, &cosign_signer)
+                .unwrap();
+            cosign_verifier
+                .verify_file(repo_dir.join("Packages.gz"))
+                .unwrap();
+            for (_, per_arch_packages) in repository.iter() {
+                for control in per_arch_packages.packages.iter() {
+                    cosign_verifier
+                        .verify_file(repo_dir.join(&control.filename))
+                        .unwrap();
+                }
+            }
+            // `opkg` signature is intact.
+            let packages = std::fs::read(repo_dir.join("Packages")).unwrap();
+            let signature =
+                ksign::Signature::read_from_file(repo_dir.join("Packages.sig")).unwrap();
+            verifying_key.verify(&packages, &signature).unwrap();
+            Ok(())
+        });
+    }
+
     #[ignore]
     #[test]
     fn opkg_installs_from_repo() {
@@ -199,8 +262,9 @@ mod tests {
                 format!("src/gz test file://{}\n", repo_dir.display()),
             )
             .unwrap();
+            let fingerprint = signing_key.sign(&[]).fingerprint();
             verifying_key
-                .write_to_file(format!("/etc/opkg/keys/{}", verifying_key.fingerprint()))
+                .write_to_file(format!("/etc/opkg/keys/{}", fingerprint))
                 .unwrap();
             Command::new("cat")
                 .arg("/etc/opkg/customfeeds.conf")
@@ -0,0 +1,263 @@
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fs::set_permissions;
+use std::fs::Permissions;
+use std::io::ErrorKind;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+/// Maintainer scripts that `opkg` runs before/after package installation/removal.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(test, derive(arbitrary::Arbitrary))]
+pub struct MaintainerScripts {
+    pub preinst: Option<Script>,
+    pub postinst: Option<Script>,
+    pub prerm: Option<Script>,
+    pub postrm: Option<Script>,
+}
+
+impl MaintainerScripts {
+    pub fn get(&self, kind: ScriptKind) -> Option<&Script> {
+        match kind {
+            ScriptKind::Preinst => self.preinst.as_ref(),
+            ScriptKind::Postinst => self.postinst.as_ref(),
+            ScriptKind::Prerm => self.prerm.as_ref(),
+            ScriptKind::Postrm => self.postrm.as_ref(),
+        }
+    }
+
+    /// Scripts and their file names in `control.tar.gz`.
+    pub fn iter(&self) -> impl Iterator<Item = (ScriptKind, &Script)> {
+        ScriptKind::ALL
+            .into_iter()
+            .filter_map(|kind| self.get(kind).map(|script| (kind, script)))
+    }
+
+    /// Run the script the same way `opkg` does when installing into `root`.
+    ///
+    /// The script is written to a temporary executable file and run directly, so that the
+    /// shebang line is respected.
+    pub fn run(&self, kind: ScriptKind, root: &Path) -> Result<(), std::io::Error> {
+        let Some(script) = self.get(kind) else {
+            return Ok(());
+        };
+        let workdir = TempDir::new()?;
+        let path = workdir.path().join(kind.file_name());
+        std::fs::write(&path, script.to_string())?;
+        set_permissions(&path, Permissions::from_mode(0o755))?;
+        let status = run_executable(
+            Command::new(&path)
+                .arg(kind.action())
+                .envs(environment(root)),
+        )?;
+        if !status.success() {
+            return Err(std::io::Error::other(format!(
+                "{} failed: {}",
+                kind.file_name(),
+                status
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Run freshly written executable.
+///
+/// Other threads might fork while the file is still open for writing; in that case `exec` fails
+/// with `ETXTBSY` until the forked process calls `exec` too.
+fn run_executable(command: &mut Command) -> Result<ExitStatus, std::io::Error> {
+    let mut attempts = 0;
+    loop {
+        match command.status() {
+            Err(e) if e.kind() == ErrorKind::ExecutableFileBusy && attempts < MAX_ATTEMPTS => {
+                attempts += 1;
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            other => return other,
+        }
+    }
+}
+
+/// Environment variables that `opkg` sets for maintainer scripts.
+///
+/// `IPKG_INSTROOT` is empty when installing on the running system, otherwise it is the path to
+/// the root file system (that is how OpenWrt build system sets this variable when building an
+/// image).
+pub fn environment(root: &Path) -> Vec<(&'static str, OsString)> {
+    let mut env = vec![("PKG_ROOT", root.as_os_str().to_os_string())];
+    if root == Path::new("/") {
+        env.push(("IPKG_INSTROOT", OsString::new()));
+    } else {
+        env.push(("IPKG_INSTROOT", root.as_os_str().to_os_string()));
+        env.push(("IPKG_OFFLINE_ROOT", root.as_os_str().to_os_string()));
+        env.push(("OFFLINE_ROOT", root.as_os_str().to_os_string()));
+    }
+    env
+}
+
+/// Maintainer script.
+///
+/// If the contents start with a shebang the script is used as is. Otherwise the contents are
+/// wrapped in a POSIX shell script with OpenWrt guards:
+/// - the script does nothing when `IPKG_NO_SCRIPT=1`,
+/// - unless `run_offline` is set, the script does nothing when `IPKG_INSTROOT` is not empty,
+///   i.e. when the package is installed into an image rather than on the running system.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(test, derive(arbitrary::Arbitrary))]
+pub struct Script {
+    pub contents: String,
+    pub run_offline: bool,
+}
+
+impl Script {
+    pub fn new(contents: String) -> Self {
+        Self {
+            contents,
+            run_offline: false,
+        }
+    }
+}
+
+impl Display for Script {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if self.contents.starts_with("#!") {
+            return f.write_str(&self.contents);
+        }
+        writeln!(f, "#!/bin/sh")?;
+        writeln!(f, "[ \"${{IPKG_NO_SCRIPT}}\" = \"1\" ] && exit 0")?;
+        if !self.run_offline {
+            writeln!(f, "[ -n \"${{IPKG_INSTROOT}}\" ] && exit 0")?;
+        }
+        f.write_str(&self.contents)?;
+        if !self.contents.ends_with('\n') {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ScriptKind {
+    Preinst,
+    Postinst,
+    Prerm,
+    Postrm,
+}
+
+impl ScriptKind {
+    pub const ALL: [Self; 4] = [Self::Preinst, Self::Postinst, Self::Prerm, Self::Postrm];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Preinst => "preinst",
+            Self::Postinst => "postinst",
+            Self::Prerm => "prerm",
+            Self::Postrm => "postrm",
+        }
+    }
+
+    /// The first argument that `opkg` passes to the script.
+    pub fn action(self) -> &'static str {
+        match self {
+            Self::Preinst => "install",
+            Self::Postinst => "configure",
+            Self::Prerm | Self::Postrm => "remove",
+        }
+    }
+}
+
+const MAX_ATTEMPTS: usize = 10;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_guard() {
+        let workdir = TempDir::new().unwrap();
+        let output = workdir.path().join("output");
+        let root = workdir.path().join("root");
+        let script = format!(
+            "echo \"$(basename \"$0\") $1 $PKG_ROOT\" > {}",
+            output.display()
+        );
+        // Installing into an image.
+        let scripts = MaintainerScripts {
+            postinst: Some(Script::new(script.clone())),
+            ..Default::default()
+        };
+        scripts.run(ScriptKind::Postinst, root.as_path()).unwrap();
+        assert!(!output.exists());
+        // The same script with the guard removed.
+        let scripts = MaintainerScripts {
+            postinst: Some(Script {
+                contents: script,
+                run_offline: true,
+            }),
+            ..Default::default()
+        };
+        scripts.run(ScriptKind::Postinst, root.as_path()).unwrap();
+        assert_eq!(
+            format!("postinst configure {}\n", root.display()),
+            std::fs::read_to_string(output.as_path()).unwrap()
+        );
+        // `prerm` is not defined.
+        scripts.run(ScriptKind::Prerm, root.as_path()).unwrap();
+    }
+
+    #[test]
+    fn running_system_environment() {
+        let env = environment(Path::new("/"));
+        assert!(env.contains(&("PKG_ROOT", "/".into())));
+        assert!(env.contains(&("IPKG_INSTROOT", OsString::new())));
+        assert!(env.iter().all(|(name, _)| !name.ends_with("OFFLINE_ROOT")));
+    }
+
+    #[test]
+    fn run_offline() {
+        let workdir = TempDir::new().unwrap();
+        let output = workdir.path().join("output");
+        let scripts = MaintainerScripts {
+            prerm: Some(Script {
+                contents: format!("echo \"$IPKG_INSTROOT\" > {}", output.display()),
+                run_offline: true,
+            }),
+            ..Default::default()
+        };
+        scripts.run(ScriptKind::Prerm, workdir.path()).unwrap();
+        assert_eq!(
+            format!("{}\n", workdir.path().display()),
+            std::fs::read_to_string(output.as_path()).unwrap()
+        );
+    }
+
+    #[test]
+    fn exit_status() {
+        let workdir = TempDir::new().unwrap();
+        for contents in ["false", "exit 3", "#!/bin/sh\nexit 1\n"] {
+            let scripts = MaintainerScripts {
+                postinst: Some(Script {
+                    contents: contents.into(),
+                    run_offline: true,
+                }),
+                ..Default::default()
+            };
+            assert!(
+                scripts.run(ScriptKind::Postinst, workdir.path()).is_err(),
+                "contents = {:?}",
+                contents
+            );
+        }
+        let scripts = MaintainerScripts {
+            postinst: Some(Script::new("#!/bin/sh\necho \"$1\"\n".into())),
+            ..Default::default()
+        };
+        scripts.run(ScriptKind::Postinst, workdir.path()).unwrap();
+    }
+}
@@ -1,15 +1,25 @@
 pub mod archive;
+pub mod artifact;
+#[cfg(feature = "attest")]
+pub mod attest;
 pub mod compress;
 pub mod cpio;
+#[cfg(feature = "deb")]
 pub mod deb;
 pub mod error;
 pub mod hash;
+#[cfg(feature = "ipk")]
 pub mod ipk;
+#[cfg(feature = "macos")]
 pub mod macos;
+#[cfg(feature = "msix")]
 pub mod msix;
+#[cfg(feature = "pkg")]
 pub mod pkg;
+#[cfg(feature = "rpm")]
 pub mod rpm;
 pub mod sign;
 #[cfg(test)]
 pub mod test;
+#[cfg(feature = "macos")]
 pub mod xar;
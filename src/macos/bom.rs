@@ -20,6 +20,8 @@ use std::path::PathBuf;
 use normalize_path::NormalizePath;
 use walkdir::WalkDir;
 
+use crate::archive::ModePolicy;
+
 #[cfg_attr(test, derive(arbitrary::Arbitrary, PartialEq, Eq, Debug))]
 pub struct Bom {
     nodes: Nodes,
@@ -31,7 +33,14 @@ impl Bom {
     }
 
     pub fn from_directory<P: AsRef<Path>>(directory: P) -> Result<Self, Error> {
-        let nodes = Nodes::from_directory(directory)?;
+        Self::from_directory_with_mode_policy(directory, &Default::default())
+    }
+
+    pub fn from_directory_with_mode_policy<P: AsRef<Path>>(
+        directory: P,
+        mode_policy: &ModePolicy,
+    ) -> Result<Self, Error> {
+        let nodes = Nodes::from_directory(directory, mode_policy)?;
         Ok(Self { nodes })
     }
 
@@ -69,7 +78,7 @@ impl Bom {
         // paths
         let num_paths = {
             let edges = self.nodes.edges();
-            eprintln!("write edges {:?}", edges);
+            log::debug!("write edges {:?}", edges);
             let num_paths = edges.len() as u32;
             let mut roots = Vec::new();
             let mut all_paths = Vec::new();
@@ -116,7 +125,7 @@ impl Bom {
             for (j, (parent, last_index, paths)) in all_paths.into_iter().enumerate() {
                 let i = blocks.write_block(writer.by_ref(), |writer| paths.write(writer))?;
                 debug_assert!(i == block_index + j as u32);
-                eprintln!("write index {} paths {:?}", i, paths);
+                log::debug!("write index {} paths {:?}", i, paths);
                 // if root
                 if *parent == 0 {
                     // take the last file (can be any file probably)
@@ -160,7 +169,7 @@ impl Bom {
         header.write(writer.by_ref())?;
         let paths = self.nodes.to_paths()?;
         for (path, metadata) in paths.iter() {
-            eprintln!("write path {:?} metadata {:?}", path, metadata);
+            log::debug!("write path {:?} metadata {:?}", path, metadata);
         }
         Ok(())
     }
@@ -169,7 +178,7 @@ impl Bom {
         let mut file = Vec::new();
         reader.read_to_end(&mut file)?;
         let header = Header::read(&file[..HEADER_LEN])?;
-        eprintln!("header {header:?}");
+        log::debug!("header {header:?}");
         let index_offset = header.index_offset as usize;
         let index_len = header.index_len as usize;
         let vars_offset = header.vars_offset as usize;
@@ -182,7 +191,7 @@ impl Bom {
                 .remove(name)
                 .ok_or_else(|| Error::other(format!("{:?} is missing", name)))?;
             let bom_info = BomInfo::read(blocks.slice(index, &file)?)?;
-            eprintln!("bom info {:?}", bom_info);
+            log::debug!("bom info {:?}", bom_info);
         }
         let mut trees = VecDeque::new();
         {
@@ -190,15 +199,15 @@ impl Bom {
             let index = vars
                 .remove(name)
                 .ok_or_else(|| Error::other(format!("{:?} is missing", name)))?;
-            eprintln!("read vindex index {}", index);
+            log::debug!("read vindex index {}", index);
             let v_index = VIndex::read(blocks.slice(index, &file)?)?;
-            eprintln!("v index {:?}", v_index);
+            log::debug!("v index {:?}", v_index);
             let name: CString = c"VIndex.index".into();
             trees.push_back((name, v_index.index));
         }
         let mut paths = VecDeque::new();
         let vars = vars.vars;
-        eprintln!("vars {:?}", vars);
+        log::debug!("vars {:?}", vars);
         for (name, index) in vars.into_iter() {
             trees.push_back((name, index));
         }
@@ -206,11 +215,11 @@ impl Bom {
             let tree = match Tree::read(blocks.slice(index, &file)?) {
                 Ok(tree) => tree,
                 Err(e) => {
-                    eprintln!("failed to parse {:?} as tree: {}", name, e);
+                    log::warn!("failed to parse {:?} as tree: {}", name, e);
                     continue;
                 }
             };
-            eprintln!("tree {:?} {:?}", name.to_str(), tree);
+            log::debug!("tree {:?} {:?}", name.to_str(), tree);
             paths.push_back(tree.child);
         }
         // id -> data
@@ -223,12 +232,15 @@ impl Bom {
             }
             let path = Paths::read(blocks.slice(index, &file)?)?;
             if !path.is_leaf {
-                eprintln!(
+                log::debug!(
                     "branch id {} forward {} backward {} indices {:?}",
-                    index, path.forward, path.backward, path.indices
+                    index,
+                    path.forward,
+                    path.backward,
+                    path.indices
                 );
             }
-            eprintln!("read index {} paths {:?}", index, path);
+            log::debug!("read index {} paths {:?}", index, path);
             // is_leaf == 0 means count == 1?
             for (index0, index1) in path.indices.into_iter() {
                 let child = if !path.is_leaf {
@@ -239,7 +251,7 @@ impl Bom {
                 } else {
                     let block_bytes = blocks.slice(index0, &file)?;
                     let id = u32_read(&block_bytes[0..4]);
-                    eprintln!("id {}", id);
+                    log::debug!("id {}", id);
                     let index = u32_read(&block_bytes[4..8]);
                     let block_bytes = blocks.slice(index, &file)?;
                     let metadata = Metadata::read(block_bytes)?;
@@ -259,7 +271,7 @@ impl Bom {
                         CStr::from_bytes_with_nul(&block_bytes[4..]).map_err(Error::other)?;
                     let name = OsStr::from_bytes(name.to_bytes());
                     if !path.is_leaf {
-                        eprintln!("parent {} name {:?}", parent, name.to_str());
+                        log::debug!("parent {} name {:?}", parent, name.to_str());
                     }
                     //eprintln!("file parent {} name {}", parent, name,);
                     if let Some(mut child) = child {
@@ -279,7 +291,7 @@ impl Bom {
         let nodes = Nodes { nodes };
         let paths = nodes.to_paths()?;
         for (path, metadata) in paths.iter() {
-            eprintln!("read path {:?} metadata {:?}", path, metadata);
+            log::debug!("read path {:?} metadata {:?}", path, metadata);
         }
         Ok(Self { nodes })
     }
@@ -314,9 +326,9 @@ impl BigEndianIo for Header {
         let index_len = u32_read(&file[20..24]);
         let vars_offset = u32_read(&file[24..28]);
         let vars_len = u32_read(&file[28..32]);
-        eprintln!("vars offset {} len {}", vars_offset, vars_len);
-        eprintln!("index offset {} len {}", index_offset, index_len);
-        eprintln!("num non null blocks {}", num_non_null_blocks);
+        log::debug!("vars offset {} len {}", vars_offset, vars_len);
+        log::debug!("index offset {} len {}", index_offset, index_len);
+        log::debug!("num non null blocks {}", num_non_null_blocks);
         Ok(Self {
             num_non_null_blocks,
             index_offset,
@@ -823,11 +835,14 @@ impl Nodes {
         edges
     }
 
-    fn from_directory<P: AsRef<Path>>(directory: P) -> Result<Self, Error> {
+    fn from_directory<P: AsRef<Path>>(
+        directory: P,
+        mode_policy: &ModePolicy,
+    ) -> Result<Self, Error> {
         let directory = directory.as_ref();
         let mut nodes: HashMap<PathBuf, Node> = HashMap::new();
         let mut id: u32 = 1;
-        for entry in WalkDir::new(directory).into_iter() {
+        for entry in WalkDir::new(directory).sort_by_file_name().into_iter() {
             let entry = entry?;
             let entry_path = entry
                 .path()
@@ -841,6 +856,9 @@ impl Nodes {
             let dirname = relative_path.parent();
             let basename = relative_path.file_name();
             let metadata = std::fs::metadata(entry.path())?;
+            let mode = mode_policy.mode(&relative_path, &metadata);
+            let mut metadata: Metadata = metadata.try_into()?;
+            metadata.mode = mode as u16;
             let node = Node {
                 id,
                 parent: match dirname {
@@ -851,7 +869,7 @@ impl Nodes {
                     Some(s) => s.into(),
                     None => relative_path.clone().into(),
                 },
-                metadata: metadata.try_into()?,
+                metadata,
             };
             nodes.insert(relative_path, node);
             id += 1;
@@ -1115,7 +1133,7 @@ mod tests {
     impl<'a> Arbitrary<'a> for Nodes {
         fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
             let directory: DirectoryOfFiles = u.arbitrary()?;
-            let nodes = Nodes::from_directory(directory.path()).unwrap();
+            let nodes = Nodes::from_directory(directory.path(), &Default::default()).unwrap();
             Ok(nodes)
             /*
             let mut nodes: Vec<Node> = u.arbitrary()?;
@@ -0,0 +1,544 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::Error;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use normalize_path::NormalizePath;
+use quick_xml::escape::escape;
+use quick_xml::events::Event;
+use quick_xml::reader::Reader;
+use walkdir::WalkDir;
+
+use crate::macos::xml;
+
+/// Application bundle (`.app` directory) found in the package contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppBundle {
+    /// Path relative to the package root, e.g. `./Applications/Foo.app`.
+    pub path: PathBuf,
+    /// `CFBundleIdentifier`.
+    pub identifier: String,
+    /// `CFBundleShortVersionString`.
+    pub short_version_string: String,
+    /// `CFBundleVersion`.
+    pub version: String,
+    /// Install the bundle where the user moved it rather than to the original location.
+    pub relocatable: bool,
+    /// Do not downgrade the bundle if the newer version is already installed.
+    pub version_checked: bool,
+    /// Only relocate the bundle to the bundles with the same identifier.
+    pub strict_identifier: bool,
+    pub overwrite_action: OverwriteAction,
+}
+
+impl AppBundle {
+    /// Find top-level application bundles in the directory.
+    ///
+    /// Nested bundles (e.g. helper applications) are installed as a part of their parent bundle.
+    pub fn find_all<P: AsRef<Path>>(directory: P) -> Result<Vec<Self>, Error> {
+        let directory = directory.as_ref();
+        let mut bundles = Vec::new();
+        let mut iter = WalkDir::new(directory).sort_by_file_name().into_iter();
+        while let Some(entry) = iter.next() {
+            let entry = entry?;
+            if !entry.file_type().is_dir() || entry.path().extension() != Some(OsStr::new("app")) {
+                continue;
+            }
+            let info_plist = entry.path().join(INFO_PLIST);
+            if !info_plist.is_file() {
+                continue;
+            }
+            let relative_path = Path::new(".").join(
+                entry
+                    .path()
+                    .strip_prefix(directory)
+                    .map_err(Error::other)?
+                    .normalize(),
+            );
+            let info = read_info_plist(&std::fs::read(&info_plist)?).map_err(|e| {
+                Error::other(format!("failed to read {}: {}", info_plist.display(), e))
+            })?;
+            bundles.push(Self::new(relative_path, info)?);
+            iter.skip_current_dir();
+        }
+        Ok(bundles)
+    }
+
+    fn new(path: PathBuf, mut info: HashMap<String, String>) -> Result<Self, Error> {
+        let identifier = info.remove("CFBundleIdentifier").ok_or_else(|| {
+            Error::other(format!("{}: CFBundleIdentifier is missing", path.display()))
+        })?;
+        let (short_version_string, version) = match (
+            info.remove("CFBundleShortVersionString"),
+            info.remove("CFBundleVersion"),
+        ) {
+            (Some(short), Some(version)) => (short, version),
+            (Some(short), None) => (short.clone(), short),
+            (None, Some(version)) => (version.clone(), version),
+            (None, None) => {
+                return Err(Error::other(format!(
+                    "{}: neither CFBundleShortVersionString nor CFBundleVersion is present",
+                    path.display()
+                )))
+            }
+        };
+        Ok(Self {
+            path,
+            identifier,
+            short_version_string,
+            version,
+            relocatable: false,
+            version_checked: true,
+            strict_identifier: true,
+            overwrite_action: Default::default(),
+        })
+    }
+
+    pub(crate) fn to_xml(&self) -> xml::Bundle {
+        xml::Bundle {
+            path: self.path.clone(),
+            id: self.identifier.clone(),
+            identifier: self.identifier.clone(),
+            short_version_string: self.short_version_string.clone(),
+            version: self.version.clone(),
+        }
+    }
+
+    pub(crate) fn to_bundle_ref(&self) -> xml::BundleRef {
+        xml::BundleRef {
+            id: self.identifier.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OverwriteAction {
+    /// Remove the files that are missing in the new version of the bundle.
+    #[default]
+    Upgrade,
+    /// Keep the files that are missing in the new version of the bundle.
+    Update,
+}
+
+impl OverwriteAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Upgrade => "upgrade",
+            Self::Update => "update",
+        }
+    }
+}
+
+/// Write component property list.
+///
+/// This is the file that `pkgbuild --analyze` generates.
+pub fn write_component_plist<W: Write>(mut writer: W, bundles: &[AppBundle]) -> Result<(), Error> {
+    writer.write_all(PLIST_HEADER.as_bytes())?;
+    writeln!(writer, "<array>")?;
+    for bundle in bundles.iter() {
+        let path = bundle
+            .path
+            .strip_prefix(".")
+            .unwrap_or(bundle.path.as_path());
+        let path = path
+            .to_str()
+            .ok_or_else(|| Error::other(format!("non utf-8 path: {}", path.display())))?;
+        writeln!(writer, "\t<dict>")?;
+        write_bool(
+            writer.by_ref(),
+            "BundleHasStrictIdentifier",
+            bundle.strict_identifier,
+        )?;
+        write_bool(writer.by_ref(), "BundleIsRelocatable", bundle.relocatable)?;
+        write_bool(
+            writer.by_ref(),
+            "BundleIsVersionChecked",
+            bundle.version_checked,
+        )?;
+        write_string(
+            writer.by_ref(),
+            "BundleOverwriteAction",
+            bundle.overwrite_action.as_str(),
+        )?;
+        write_string(writer.by_ref(), "RootRelativeBundlePath", path)?;
+        writeln!(writer, "\t</dict>")?;
+    }
+    writeln!(writer, "</array>")?;
+    writeln!(writer, "</plist>")?;
+    Ok(())
+}
+
+fn write_bool<W: Write>(mut writer: W, key: &str, value: bool) -> Result<(), Error> {
+    writeln!(writer, "\t\t<key>{}</key>", escape(key))?;
+    writeln!(writer, "\t\t<{}/>", value)?;
+    Ok(())
+}
+
+fn write_string<W: Write>(mut writer: W, key: &str, value: &str) -> Result<(), Error> {
+    writeln!(writer, "\t\t<key>{}</key>", escape(key))?;
+    writeln!(writer, "\t\t<string>{}</string>", escape(value))?;
+    Ok(())
+}
+
+/// Read top-level string values from XML or binary property list.
+fn read_info_plist(bytes: &[u8]) -> Result<HashMap<String, String>, Error> {
+    if bytes.starts_with(BINARY_PLIST_MAGIC) {
+        return read_binary_plist(bytes);
+    }
+    if bytes.starts_with(b"bplist") {
+        return Err(Error::other(format!(
+            "unsupported binary property list version {:?}, only {:?} is supported",
+            String::from_utf8_lossy(bytes.get(..8).unwrap_or(bytes)),
+            String::from_utf8_lossy(BINARY_PLIST_MAGIC)
+        )));
+    }
+    let mut reader = Reader::from_reader(bytes);
+    reader.config_mut().trim_text(true);
+    let mut buf = Vec::new();
+    let mut values = HashMap::new();
+    // <plist> -> <dict> -> <key>/<string>
+    let mut depth: usize = 0;
+    let mut key: Option<String> = None;
+    let mut in_key = false;
+    let mut in_string = false;
+    loop {
+        match reader.read_event_into(&mut buf).map_err(Error::other)? {
+            Event::Start(e) => {
+                depth += 1;
+                if depth == 3 {
+                    match e.name().as_ref() {
+                        b"key" => in_key = true,
+                        b"string" => in_string = true,
+                        // non-string value
+                        _ => key = None,
+                    }
+                }
+            }
+            Event::End(_) => {
+                if depth == 3 && in_string {
+                    key = None;
+                }
+                depth = depth.saturating_sub(1);
+                in_key = false;
+                in_string = false;
+            }
+            // <true/>, <false/>, <string/> etc.
+            Event::Empty(_) if depth == 2 => {
+                key = None;
+            }
+            Event::Text(e) => {
+                let text = e.unescape().map_err(Error::other)?;
+                if in_key {
+                    key = Some(text.into_owned());
+                } else if in_string {
+                    if let Some(key) = key.take() {
+                        values.insert(key, text.into_owned());
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(values)
+}
+
+/// Read top-level string values from binary property list (`bplist00`).
+fn read_binary_plist(bytes: &[u8]) -> Result<HashMap<String, String>, Error> {
+    let plist = BinaryPlist::new(bytes)?;
+    let offset = plist.object_offset(plist.top_object)?;
+    let (kind, len, start) = plist.object_header(offset)?;
+    if kind != 0xd {
+        return Err(Error::other(
+            "binary property list: top-level object is not a dictionary",
+        ));
+    }
+    let mut values = HashMap::new();
+    let ref_offset = |i: usize| -> Result<usize, Error> {
+        i.checked_mul(plist.ref_size)
+            .and_then(|x| x.checked_add(start))
+            .ok_or_else(invalid_binary_plist)
+    };
+    for i in 0..len {
+        let key_ref = plist.read_uint(ref_offset(i)?, plist.ref_size)?;
+        let value_ref = plist.read_uint(
+            ref_offset(len.checked_add(i).ok_or_else(invalid_binary_plist)?)?,
+            plist.ref_size,
+        )?;
+        let Some(key) = plist.read_string(key_ref)? else {
+            continue;
+        };
+        // non-string value
+        let Some(value) = plist.read_string(value_ref)? else {
+            continue;
+        };
+        values.insert(key, value);
+    }
+    Ok(values)
+}
+
+struct BinaryPlist<'a> {
+    bytes: &'a [u8],
+    offset_size: usize,
+    ref_size: usize,
+    num_objects: usize,
+    top_object: usize,
+    offset_table: usize,
+}
+
+impl<'a> BinaryPlist<'a> {
+    fn new(bytes: &'a [u8]) -> Result<Self, Error> {
+        if bytes.len() < BINARY_PLIST_MAGIC.len() + BINARY_PLIST_TRAILER_LEN {
+            return Err(invalid_binary_plist());
+        }
+        let trailer = &bytes[bytes.len() - BINARY_PLIST_TRAILER_LEN..];
+        let read_u64 = |i: usize| -> Result<usize, Error> {
+            let value = u64::from_be_bytes(trailer[i..i + 8].try_into().unwrap_or_default());
+            value.try_into().map_err(|_| invalid_binary_plist())
+        };
+        let plist = Self {
+            bytes,
+            offset_size: trailer[6] as usize,
+            ref_size: trailer[7] as usize,
+            num_objects: read_u64(8)?,
+            top_object: read_u64(16)?,
+            offset_table: read_u64(24)?,
+        };
+        if !(1..=8).contains(&plist.offset_size) || !(1..=8).contains(&plist.ref_size) {
+            return Err(invalid_binary_plist());
+        }
+        Ok(plist)
+    }
+
+    fn read_uint(&self, offset: usize, size: usize) -> Result<usize, Error> {
+        let end = offset.checked_add(size).ok_or_else(invalid_binary_plist)?;
+        let bytes = self
+            .bytes
+            .get(offset..end)
+            .ok_or_else(invalid_binary_plist)?;
+        let value = bytes
+            .iter()
+            .fold(0_u64, |value, byte| (value << 8) | *byte as u64);
+        value.try_into().map_err(|_| invalid_binary_plist())
+    }
+
+    fn object_offset(&self, object: usize) -> Result<usize, Error> {
+        if object >= self.num_objects {
+            return Err(invalid_binary_plist());
+        }
+        let offset = object
+            .checked_mul(self.offset_size)
+            .and_then(|x| x.checked_add(self.offset_table))
+            .ok_or_else(invalid_binary_plist)?;
+        self.read_uint(offset, self.offset_size)
+    }
+
+    /// Returns object type, the number of elements and the offset of the first element.
+    fn object_header(&self, offset: usize) -> Result<(u8, usize, usize), Error> {
+        // Valid offsets are always less than the size of the file.
+        if offset >= self.bytes.len() {
+            return Err(invalid_binary_plist());
+        }
+        let marker = self.bytes[offset];
+        let kind = marker >> 4;
+        let len = marker & 0xf;
+        if len != 0xf {
+            return Ok((kind, len as usize, offset + 1));
+        }
+        // The length is stored in the following integer object.
+        let int_marker = *self
+            .bytes
+            .get(offset + 1)
+            .ok_or_else(invalid_binary_plist)?;
+        if int_marker >> 4 != 0x1 || int_marker & 0xf > 3 {
+            return Err(invalid_binary_plist());
+        }
+        let size = 1_usize << (int_marker & 0xf);
+        let len = self.read_uint(offset + 2, size)?;
+        Ok((kind, len, offset + 2 + size))
+    }
+
+    fn read_string(&self, object: usize) -> Result<Option<String>, Error> {
+        let (kind, len, start) = self.object_header(self.object_offset(object)?)?;
+        let string = match kind {
+            // ASCII
+            0x5 => {
+                let end = start.checked_add(len).ok_or_else(invalid_binary_plist)?;
+                let bytes = self
+                    .bytes
+                    .get(start..end)
+                    .ok_or_else(invalid_binary_plist)?;
+                String::from_utf8(bytes.to_vec()).map_err(|_| invalid_binary_plist())?
+            }
+            // UTF-16 big endian
+            0x6 => {
+                let end = len
+                    .checked_mul(2)
+                    .and_then(|x| x.checked_add(start))
+                    .ok_or_else(invalid_binary_plist)?;
+                let bytes = self
+                    .bytes
+                    .get(start..end)
+                    .ok_or_else(invalid_binary_plist)?;
+                let units = bytes
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+                char::decode_utf16(units)
+                    .collect::<Result<String, _>>()
+                    .map_err(|_| invalid_binary_plist())?
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(string))
+    }
+}
+
+fn invalid_binary_plist() -> Error {
+    Error::other("invalid binary property list")
+}
+
+const INFO_PLIST: &str = "Contents/Info.plist";
+const BINARY_PLIST_MAGIC: &[u8] = b"bplist00";
+const BINARY_PLIST_TRAILER_LEN: usize = 32;
+const PLIST_HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+"#;
+
+#[cfg(test)]
+mod tests {
+    use std::fs::create_dir_all;
+
+    use arbtest::arbtest;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn info_plist_read() {
+        let info = read_info_plist(INFO_PLIST_EXAMPLE.as_bytes()).unwrap();
+        assert_eq!(
+            Some("com.example.Foo"),
+            info.get("CFBundleIdentifier").map(|x| x.as_str())
+        );
+        assert_eq!(
+            Some("1.2.3"),
+            info.get("CFBundleShortVersionString").map(|x| x.as_str())
+        );
+        assert_eq!(Some("123"), info.get("CFBundleVersion").map(|x| x.as_str()));
+        assert_eq!(None, info.get("LSRequiresCarbon"));
+        assert_eq!(None, info.get("CFBundleDocumentTypes"));
+        assert_eq!(None, info.get("CFBundleTypeName"));
+    }
+
+    #[test]
+    fn binary_info_plist_read() {
+        let info = read_info_plist(BINARY_INFO_PLIST_EXAMPLE).unwrap();
+        assert_eq!(
+            Some("com.example.Foo"),
+            info.get("CFBundleIdentifier").map(|x| x.as_str())
+        );
+        assert_eq!(
+            Some("1.2.3"),
+            info.get("CFBundleShortVersionString").map(|x| x.as_str())
+        );
+        assert_eq!(Some("123"), info.get("CFBundleVersion").map(|x| x.as_str()));
+        assert_eq!(Some("Föö"), info.get("CFBundleName").map(|x| x.as_str()));
+        assert_eq!(None, info.get("LSRequiresCarbon"));
+        assert_eq!(None, info.get("CFBundleDocumentTypes"));
+        assert_eq!(None, info.get("CFBundleTypeName"));
+    }
+
+    #[test]
+    fn binary_info_plist_does_not_panic() {
+        arbtest(|u| {
+            let mut bytes = BINARY_INFO_PLIST_EXAMPLE.to_vec();
+            let len = u.int_in_range(0..=bytes.len())?;
+            bytes.truncate(len);
+            for byte in bytes.iter_mut() {
+                if u.ratio(1, 16)? {
+                    *byte = u.arbitrary()?;
+                }
+            }
+            let _ = read_info_plist(&bytes);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn binary_info_plist_unsupported_version() {
+        let error = read_info_plist(b"bplist15").unwrap_err();
+        assert!(error.to_string().contains("bplist15"), "error = {}", error);
+    }
+
+    #[test]
+    fn find_app_bundles() {
+        let workdir = TempDir::new().unwrap();
+        let app = workdir.path().join("Applications/Foo.app");
+        let helper = app.join("Contents/Helpers/Bar.app");
+        create_dir_all(app.join("Contents")).unwrap();
+        create_dir_all(helper.join("Contents")).unwrap();
+        create_dir_all(workdir.path().join("Applications/NotABundle.app")).unwrap();
+        std::fs::write(app.join(INFO_PLIST), INFO_PLIST_EXAMPLE).unwrap();
+        std::fs::write(helper.join(INFO_PLIST), INFO_PLIST_EXAMPLE).unwrap();
+        let bundles = AppBundle::find_all(workdir.path()).unwrap();
+        assert_eq!(1, bundles.len(), "bundles = {:?}", bundles);
+        assert_eq!(Path::new("./Applications/Foo.app"), bundles[0].path);
+        assert_eq!("com.example.Foo", bundles[0].identifier);
+        let mut component_plist = Vec::new();
+        write_component_plist(&mut component_plist, &bundles).unwrap();
+        let component_plist = String::from_utf8(component_plist).unwrap();
+        assert!(
+            component_plist.contains("<string>Applications/Foo.app</string>"),
+            "component plist = {}",
+            component_plist
+        );
+    }
+
+    /// [`INFO_PLIST_EXAMPLE`] in binary format with non-ASCII `CFBundleName` added.
+    const BINARY_INFO_PLIST_EXAMPLE: &[u8] = b"\
+        \x62\x70\x6c\x69\x73\x74\x30\x30\xd6\x01\x02\x03\x04\x05\x06\x07\
+        \x0b\x0c\x0d\x0e\x0f\x5f\x10\x15\x43\x46\x42\x75\x6e\x64\x6c\x65\
+        \x44\x6f\x63\x75\x6d\x65\x6e\x74\x54\x79\x70\x65\x73\x5f\x10\x12\
+        \x43\x46\x42\x75\x6e\x64\x6c\x65\x49\x64\x65\x6e\x74\x69\x66\x69\
+        \x65\x72\x5c\x43\x46\x42\x75\x6e\x64\x6c\x65\x4e\x61\x6d\x65\x5f\
+        \x10\x1a\x43\x46\x42\x75\x6e\x64\x6c\x65\x53\x68\x6f\x72\x74\x56\
+        \x65\x72\x73\x69\x6f\x6e\x53\x74\x72\x69\x6e\x67\x5f\x10\x0f\x43\
+        \x46\x42\x75\x6e\x64\x6c\x65\x56\x65\x72\x73\x69\x6f\x6e\x5f\x10\
+        \x10\x4c\x53\x52\x65\x71\x75\x69\x72\x65\x73\x43\x61\x72\x62\x6f\
+        \x6e\xa1\x08\xd1\x09\x0a\x5f\x10\x10\x43\x46\x42\x75\x6e\x64\x6c\
+        \x65\x54\x79\x70\x65\x4e\x61\x6d\x65\x58\x44\x6f\x63\x75\x6d\x65\
+        \x6e\x74\x5f\x10\x0f\x63\x6f\x6d\x2e\x65\x78\x61\x6d\x70\x6c\x65\
+        \x2e\x46\x6f\x6f\x63\x00\x46\x00\xf6\x00\xf6\x55\x31\x2e\x32\x2e\
+        \x33\x53\x31\x32\x33\x09\x08\x15\x2d\x42\x4f\x6c\x7e\x91\x93\x96\
+        \xa9\xb2\xc4\xcb\xd1\xd5\x00\x00\x00\x00\x00\x00\x01\x01\x00\x00\
+        \x00\x00\x00\x00\x00\x10\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\
+        \x00\x00\x00\x00\x00\xd6\
+    ";
+
+    const INFO_PLIST_EXAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>CFBundleDocumentTypes</key>
+	<array>
+		<dict>
+			<key>CFBundleTypeName</key>
+			<string>Document</string>
+		</dict>
+	</array>
+	<key>CFBundleIdentifier</key>
+	<string>com.example.Foo</string>
+	<key>LSRequiresCarbon</key>
+	<true/>
+	<key>CFBundleShortVersionString</key>
+	<string>1.2.3</string>
+	<key>CFBundleVersion</key>
+	<string>123</string>
+</dict>
+</plist>
+"#;
+}
@@ -1,8 +1,11 @@
 mod bom;
+mod bundle;
 mod package;
 mod package_info;
 mod signer;
 
 pub use self::bom::*;
+pub use self::bundle::*;
+pub use self::package::*;
 pub use self::package_info::*;
 pub use self::signer::*;
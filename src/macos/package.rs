@@ -1,32 +1,95 @@
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::fs::create_dir_all;
+use std::fs::set_permissions;
 use std::fs::File;
+use std::fs::Permissions;
 use std::io::Error;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Seek;
 use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::symlink;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Component;
 use std::path::Path;
 
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
+use normalize_path::NormalizePath;
 use tempfile::TempDir;
 
+use crate::archive::normalize;
+use crate::archive::ModePolicy;
+use crate::archive::FILE_TYPE_MASK;
+use crate::artifact::ArtifactFormat;
+use crate::artifact::ArtifactName;
+use crate::compress::AnyDecoder;
+use crate::cpio::CpioArchive;
 use crate::cpio::CpioBuilder;
+use crate::macos::write_component_plist;
 use crate::macos::xml;
+use crate::macos::AppBundle;
 use crate::macos::Bom;
+use crate::macos::OverwriteAction;
 use crate::macos::PackageSigner;
 use crate::xar::SignedXarBuilder;
+use crate::xar::XarArchive;
 use crate::xar::XarCompression;
 
 #[cfg_attr(test, derive(arbitrary::Arbitrary, PartialEq, Eq, Clone, Debug))]
 pub struct Package {
     pub identifier: String,
     pub version: String,
+    /// Application bundles that the installer updates where the user has moved them.
+    ///
+    /// Bundles are specified by their identifiers (`CFBundleIdentifier`) or by their paths
+    /// relative to the package root, e.g. `Applications/Foo.app`.
+    pub relocatable: BTreeSet<String>,
 }
 
 impl Package {
+    pub fn artifact_name(&self) -> ArtifactName {
+        ArtifactName {
+            name: self.identifier.clone(),
+            version: self.version.clone(),
+            arch: None,
+            format: ArtifactFormat::MacosPkg,
+        }
+    }
+
+    /// Conventional package file name: `<identifier>-<version>.pkg`.
+    pub fn file_name(&self) -> String {
+        self.artifact_name().to_string()
+    }
+
     pub fn write<W: Write, P: AsRef<Path>>(
         &self,
         writer: W,
         directory: P,
         signer: &PackageSigner,
     ) -> Result<(), Error> {
+        self.write_with_mode_policy(writer, directory, signer, &Default::default())
+    }
+
+    /// Write the package normalizing file modes according to `mode_policy`.
+    pub fn write_with_mode_policy<W: Write, P: AsRef<Path>>(
+        &self,
+        writer: W,
+        directory: P,
+        signer: &PackageSigner,
+        mode_policy: &ModePolicy,
+    ) -> Result<(), Error> {
+        let directory = directory.as_ref();
+        let bundles = self.bundles(directory)?;
+        let bundle_refs = |f: fn(&AppBundle) -> bool| -> Vec<xml::BundleRef> {
+            bundles
+                .iter()
+                .filter(|bundle| f(bundle))
+                .map(AppBundle::to_bundle_ref)
+                .collect()
+        };
         let info = xml::PackageInfo {
             format_version: 2,
             install_location: Some("/".into()),
@@ -39,26 +102,38 @@ impl Package {
                 install_kb: 0,
             },
             relocatable: Default::default(),
-            bundles: Default::default(),
-            bundle_version: Default::default(),
-            upgrade_bundle: Default::default(),
-            update_bundle: Default::default(),
+            bundles: bundles.iter().map(AppBundle::to_xml).collect(),
+            bundle_version: xml::BundleVersion {
+                bundles: bundle_refs(|bundle| bundle.version_checked),
+            },
+            upgrade_bundle: xml::UpgradeBundle {
+                bundles: bundle_refs(|bundle| bundle.overwrite_action == OverwriteAction::Upgrade),
+            },
+            update_bundle: xml::UpdateBundle {
+                bundles: bundle_refs(|bundle| bundle.overwrite_action == OverwriteAction::Update),
+            },
             atomic_update_bundle: Default::default(),
-            strict_identifier: Default::default(),
-            relocate: Default::default(),
+            strict_identifier: xml::StrictIdentifier {
+                bundles: bundle_refs(|bundle| bundle.strict_identifier),
+            },
+            relocate: xml::Relocate {
+                bundles: bundle_refs(|bundle| bundle.relocatable),
+            },
             scripts: Default::default(),
         };
         let workdir = TempDir::new()?;
         let package_info_file = workdir.path().join("PackageInfo");
         info.write(File::create(&package_info_file)?)?;
-        let directory = directory.as_ref();
-        let bom = Bom::from_directory(directory)?;
+        let bom = Bom::from_directory_with_mode_policy(directory, mode_policy)?;
         let bom_file = workdir.path().join("Bom");
         bom.write(File::create(&bom_file)?)?;
         let payload_file = workdir.path().join("Payload");
-        CpioBuilder::from_directory(
+        // Extended attributes go to AppleDouble members of the payload and are not listed in the
+        // BOM, file flags are not preserved.
+        CpioBuilder::from_directory_with_mode_policy(
             ZlibEncoder::new(File::create(&payload_file)?, Compression::best()),
             directory,
+            mode_policy,
         )?
         .finish()?;
         let mut xar = SignedXarBuilder::new(writer, signer);
@@ -72,8 +147,160 @@ impl Package {
         xar.sign(signer)?;
         Ok(())
     }
+
+    /// Write component property list that describes the application bundles in `directory`.
+    ///
+    /// This is the file that `pkgbuild --analyze` generates. It is not a part of the package.
+    pub fn component_plist<W: Write, P: AsRef<Path>>(
+        &self,
+        writer: W,
+        directory: P,
+    ) -> Result<(), Error> {
+        write_component_plist(writer, &self.bundles(directory.as_ref())?)
+    }
+
+    /// Find application bundles in `directory` and apply the package settings to them.
+    fn bundles(&self, directory: &Path) -> Result<Vec<AppBundle>, Error> {
+        let mut bundles = AppBundle::find_all(directory)?;
+        for bundle in bundles.iter_mut() {
+            let path = normalize(&bundle.path);
+            bundle.relocatable = self.relocatable.iter().any(|selector| {
+                *selector == bundle.identifier || normalize(Path::new(selector)) == path
+            });
+        }
+        Ok(bundles)
+    }
+
+    /// Read the component package and expand it into `directory`.
+    ///
+    /// The layout of the directory is the same as the one produced by `pkgutil --expand-full`:
+    /// the payload is extracted into `Payload` subdirectory and the scripts are extracted into
+    /// `Scripts` subdirectory.
+    ///
+    /// Product archives (the ones that contain other packages) are not supported.
+    pub fn read<R: Read + Seek, P: AsRef<Path>>(
+        reader: R,
+        directory: P,
+    ) -> Result<ExpandedPackage, Error> {
+        let directory = directory.as_ref();
+        let mut xar = XarArchive::new(reader)?;
+        let mut info = None;
+        let mut distribution = None;
+        let mut bom = None;
+        for mut entry in xar.files() {
+            let name = entry.file().name.clone();
+            match name.as_os_str().as_bytes() {
+                b"PackageInfo" => info = Some(xml::PackageInfo::read(entry.reader()?)?),
+                b"Distribution" => distribution = Some(xml::Distribution::read(entry.reader()?)?),
+                b"Bom" => bom = Some(Bom::read(entry.reader()?)?),
+                b"Payload" => expand(entry.reader()?, &directory.join("Payload"))?,
+                b"Scripts" => expand(entry.reader()?, &directory.join("Scripts"))?,
+                _ => log::debug!("skipping {:?}", name),
+            }
+        }
+        let info = info.ok_or_else(|| Error::other("PackageInfo is missing"))?;
+        let bom = bom.ok_or_else(|| Error::other("Bom is missing"))?;
+        Ok(ExpandedPackage {
+            package: Package {
+                identifier: info.identifier.clone(),
+                version: info.version.clone(),
+                relocatable: info
+                    .relocate
+                    .bundles
+                    .iter()
+                    .map(|bundle| bundle.id.clone())
+                    .collect(),
+            },
+            info,
+            distribution,
+            bom,
+        })
+    }
+}
+
+/// Package metadata that [`Package::read`] returns.
+pub struct ExpandedPackage {
+    pub package: Package,
+    pub info: xml::PackageInfo,
+    pub distribution: Option<xml::Distribution>,
+    /// Bill of materials, i.e. the paths and the metadata of the payload files.
+    pub bom: Bom,
+}
+
+/// Extract compressed CPIO archive into `directory`.
+fn expand<R: Read>(reader: R, directory: &Path) -> Result<(), Error> {
+    create_dir_all(directory)?;
+    let mut archive = CpioArchive::new(AnyDecoder::new(reader));
+    for entry in archive.iter() {
+        let mut entry = entry?;
+        let path = entry.name.normalize();
+        if path
+            .components()
+            .any(|comp| !matches!(comp, Component::Normal(..)))
+        {
+            return Err(Error::other(format!("invalid path {:?}", entry.name)));
+        }
+        if path.as_os_str().is_empty() {
+            // `.`
+            continue;
+        }
+        // Previously extracted symlinks must not redirect the writes outside of `directory`.
+        reject_symlinks(directory, &path)?;
+        let path = directory.join(path);
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+        let mode = entry.header.mode;
+        match mode & FILE_TYPE_MASK {
+            FILE_TYPE_DIRECTORY => create_dir_all(&path)?,
+            FILE_TYPE_REGULAR => {
+                let mut file = File::create(&path)?;
+                std::io::copy(&mut entry.reader, &mut file)?;
+                set_permissions(&path, Permissions::from_mode(mode & 0o777))?;
+            }
+            FILE_TYPE_SYMLINK => {
+                let mut target = Vec::new();
+                entry.reader.read_to_end(&mut target)?;
+                symlink(OsStr::from_bytes(&target), &path)?;
+            }
+            _ => {
+                log::debug!(
+                    "skipping {:?}: unsupported file type {:o}",
+                    entry.name,
+                    mode
+                );
+                // The next entry starts after the contents of this one.
+                std::io::copy(&mut entry.reader, &mut std::io::sink())?;
+            }
+        }
+    }
+    Ok(())
 }
 
+/// Fail if `path` or any of its ancestors is a symlink that already exists in `directory`.
+fn reject_symlinks(directory: &Path, path: &Path) -> Result<(), Error> {
+    let mut current = directory.to_path_buf();
+    for comp in path.components() {
+        current.push(comp);
+        match current.symlink_metadata() {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                return Err(Error::other(format!(
+                    "invalid path {:?}: {:?} is a symlink",
+                    path, current
+                )));
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+const FILE_TYPE_DIRECTORY: u32 = 0o040000;
+const FILE_TYPE_REGULAR: u32 = 0o100000;
+const FILE_TYPE_SYMLINK: u32 = 0o120000;
+
 #[cfg(test)]
 mod tests {
     use std::fs::File;
@@ -82,13 +309,196 @@ mod tests {
 
     use arbtest::arbtest;
     use tempfile::TempDir;
+    use walkdir::WalkDir;
 
     use super::*;
+    use crate::cpio::OdcHeader;
     use crate::macos::PackageSigner;
     use crate::macos::SigningKey;
     use crate::test::prevent_concurrency;
     use crate::test::DirectoryOfFiles;
 
+    #[test]
+    fn write_read() {
+        let (signing_key, _verifying_key) = SigningKey::generate("wolfpack".into()).unwrap();
+        let signer = PackageSigner::new(signing_key);
+        arbtest(|u| {
+            let workdir = TempDir::new().unwrap();
+            let package_file = workdir.path().join("test.pkg");
+            let expanded_dir = workdir.path().join("expanded");
+            let package = Package {
+                identifier: "com.example.test".into(),
+                version: "1.0".into(),
+                relocatable: Default::default(),
+            };
+            let directory: DirectoryOfFiles = u.arbitrary()?;
+            package
+                .write(
+                    File::create(package_file.as_path()).unwrap(),
+                    directory.path(),
+                    &signer,
+                )
+                .unwrap();
+            let expanded =
+                Package::read(File::open(package_file.as_path()).unwrap(), &expanded_dir).unwrap();
+            assert_eq!(package, expanded.package);
+            assert!(expanded.distribution.is_none());
+            let bom_paths = expanded.bom.paths().unwrap();
+            for entry in WalkDir::new(directory.path()).into_iter() {
+                let entry = entry.unwrap();
+                let path = entry.path().strip_prefix(directory.path()).unwrap();
+                if path == Path::new("") || entry.path().is_dir() {
+                    continue;
+                }
+                assert!(
+                    bom_paths
+                        .keys()
+                        .any(|bom_path| bom_path.normalize() == path),
+                    "path = {:?}, bom = {:?}",
+                    path,
+                    bom_paths
+                );
+                assert_eq!(
+                    std::fs::read(entry.path()).unwrap(),
+                    std::fs::read(expanded_dir.join("Payload").join(path)).unwrap(),
+                    "path = {:?}",
+                    path
+                );
+            }
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn write_app_bundles() {
+        let (signing_key, _verifying_key) = SigningKey::generate("wolfpack".into()).unwrap();
+        let signer = PackageSigner::new(signing_key);
+        let workdir = TempDir::new().unwrap();
+        let directory = workdir.path().join("root");
+        for name in ["Foo", "Bar"] {
+            let contents = directory.join(format!("Applications/{}.app/Contents", name));
+            create_dir_all(&contents).unwrap();
+            std::fs::write(
+                contents.join("Info.plist"),
+                INFO_PLIST_EXAMPLE.replace("Foo", name),
+            )
+            .unwrap();
+        }
+        let package_file = workdir.path().join("test.pkg");
+        let package = Package {
+            identifier: "com.example.test".into(),
+            version: "1.0".into(),
+            relocatable: ["com.example.Foo".to_string()].into(),
+        };
+        package
+            .write(File::create(&package_file).unwrap(), &directory, &signer)
+            .unwrap();
+        let expanded = Package::read(
+            File::open(&package_file).unwrap(),
+            workdir.path().join("expanded"),
+        )
+        .unwrap();
+        assert_eq!(package, expanded.package);
+        let info = expanded.info;
+        assert_eq!(2, info.bundles.len());
+        assert_eq!(Path::new("./Applications/Bar.app"), info.bundles[0].path);
+        assert_eq!(Path::new("./Applications/Foo.app"), info.bundles[1].path);
+        assert_eq!("com.example.Foo", info.bundles[1].identifier);
+        assert_eq!("1.2.3", info.bundles[1].short_version_string);
+        assert_eq!("123", info.bundles[1].version);
+        for bundles in [
+            &info.bundle_version.bundles,
+            &info.upgrade_bundle.bundles,
+            &info.strict_identifier.bundles,
+        ] {
+            assert_eq!(
+                vec!["com.example.Bar", "com.example.Foo"],
+                bundles.iter().map(|x| x.id.as_str()).collect::<Vec<_>>()
+            );
+        }
+        assert_eq!(
+            vec!["com.example.Foo"],
+            info.relocate
+                .bundles
+                .iter()
+                .map(|x| x.id.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert!(info.update_bundle.bundles.is_empty());
+        let mut xar = XarArchive::new(File::open(&package_file).unwrap()).unwrap();
+        let names: Vec<_> = xar.files().map(|entry| entry.file().name.clone()).collect();
+        assert_eq!(
+            vec![
+                Path::new("PackageInfo"),
+                Path::new("Bom"),
+                Path::new("Payload")
+            ],
+            names
+        );
+        // Bundles can be selected by their paths as well.
+        let package = Package {
+            relocatable: ["/Applications/Bar.app".to_string()].into(),
+            ..package
+        };
+        let mut component_plist = Vec::new();
+        package
+            .component_plist(&mut component_plist, &directory)
+            .unwrap();
+        let component_plist = String::from_utf8(component_plist).unwrap();
+        let relocatable = "<key>BundleIsRelocatable</key>\n\t\t<true/>\n";
+        let (bar, foo) = component_plist
+            .split_once("<string>Applications/Bar.app</string>")
+            .unwrap();
+        assert!(
+            bar.contains(relocatable) && !foo.contains(relocatable),
+            "component plist = {}",
+            component_plist
+        );
+        assert!(
+            foo.contains("<string>Applications/Foo.app</string>"),
+            "component plist = {}",
+            component_plist
+        );
+    }
+
+    #[test]
+    fn expand_rejects_writes_through_symlinks() {
+        let workdir = TempDir::new().unwrap();
+        let outside_dir = workdir.path().join("outside");
+        let expanded_dir = workdir.path().join("expanded");
+        create_dir_all(&outside_dir).unwrap();
+        let template = File::create(workdir.path().join("template")).unwrap();
+        let mut header: OdcHeader = template.metadata().unwrap().try_into().unwrap();
+        let mut cpio = CpioBuilder::new(Vec::new());
+        let target = outside_dir.as_os_str().as_bytes();
+        header.mode = FILE_TYPE_SYMLINK | 0o777;
+        header.file_size = target.len() as u64;
+        cpio.write_entry(header.clone(), "a", target).unwrap();
+        header.mode = FILE_TYPE_REGULAR | 0o4755;
+        header.file_size = 5;
+        cpio.write_entry(header, "a/passwd", &b"hello"[..]).unwrap();
+        let archive = cpio.finish().unwrap();
+        assert!(expand(&archive[..], &expanded_dir).is_err());
+        assert!(!outside_dir.join("passwd").exists());
+        assert!(expanded_dir.join("a").is_symlink());
+    }
+
+    #[test]
+    fn expand_drops_special_mode_bits() {
+        let workdir = TempDir::new().unwrap();
+        let expanded_dir = workdir.path().join("expanded");
+        let template = File::create(workdir.path().join("template")).unwrap();
+        let mut header: OdcHeader = template.metadata().unwrap().try_into().unwrap();
+        let mut cpio = CpioBuilder::new(Vec::new());
+        header.mode = FILE_TYPE_REGULAR | 0o6755;
+        header.file_size = 5;
+        cpio.write_entry(header, "file", &b"hello"[..]).unwrap();
+        let archive = cpio.finish().unwrap();
+        expand(&archive[..], &expanded_dir).unwrap();
+        let metadata = expanded_dir.join("file").metadata().unwrap();
+        assert_eq!(0o755, metadata.permissions().mode() & 0o7777);
+    }
+
     #[ignore]
     #[test]
     fn macos_installer_installs_random_package() {
@@ -151,4 +561,18 @@ mod tests {
         })
         .budget(Duration::from_secs(5));
     }
+
+    const INFO_PLIST_EXAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>CFBundleIdentifier</key>
+	<string>com.example.Foo</string>
+	<key>CFBundleShortVersionString</key>
+	<string>1.2.3</string>
+	<key>CFBundleVersion</key>
+	<string>123</string>
+</dict>
+</plist>
+"#;
 }
@@ -1,7 +1,10 @@
-use std::io::Write;
+use std::io::BufReader;
 use std::io::Error;
+use std::io::Read;
+use std::io::Write;
 use std::path::PathBuf;
 
+use quick_xml::de::from_reader;
 use quick_xml::se::to_writer;
 use serde::Deserialize;
 use serde::Serialize;
@@ -15,17 +18,17 @@ pub mod xml {
     pub struct PackageInfo {
         #[serde(rename = "@format-version")]
         pub format_version: u64,
-        #[serde(rename = "@install-location")]
+        #[serde(rename = "@install-location", skip_serializing_if = "Option::is_none")]
         pub install_location: Option<PathBuf>,
         #[serde(rename = "@identifier")]
         pub identifier: String,
         #[serde(rename = "@version")]
         pub version: String,
-        #[serde(rename = "@generator_version")]
+        #[serde(rename = "@generator_version", skip_serializing_if = "Option::is_none")]
         pub generator_version: Option<String>,
         #[serde(rename = "@auth")]
         pub auth: Auth,
-        #[serde(rename = "@relocatable")]
+        #[serde(rename = "@relocatable", skip_serializing_if = "Option::is_none")]
         pub relocatable: Option<bool>,
         pub payload: Payload,
         #[serde(rename = "bundle", default)]
@@ -47,6 +50,10 @@ pub mod xml {
     }
 
     impl PackageInfo {
+        pub fn read<R: Read>(reader: R) -> Result<Self, Error> {
+            from_reader(BufReader::new(reader)).map_err(Error::other)
+        }
+
         pub fn write<W: Write>(&self, mut writer: W) -> Result<(), Error> {
             let mut s = String::new();
             to_writer(&mut s, self).map_err(Error::other)?;
@@ -82,51 +89,51 @@ pub mod xml {
     #[derive(Serialize, Deserialize, Debug, Default)]
     #[serde(rename = "bundle-version")]
     pub struct BundleVersion {
-        #[serde(rename = "bundle")]
+        #[serde(rename = "bundle", default)]
         pub bundles: Vec<BundleRef>,
     }
 
     #[derive(Serialize, Deserialize, Debug, Default)]
     #[serde(rename = "upgrade-bundle")]
     pub struct UpgradeBundle {
-        #[serde(rename = "bundle")]
+        #[serde(rename = "bundle", default)]
         pub bundles: Vec<BundleRef>,
     }
 
     #[derive(Serialize, Deserialize, Debug, Default)]
     #[serde(rename = "update-bundle")]
     pub struct UpdateBundle {
-        #[serde(rename = "bundle")]
+        #[serde(rename = "bundle", default)]
         pub bundles: Vec<BundleRef>,
     }
 
     #[derive(Serialize, Deserialize, Debug, Default)]
     #[serde(rename = "atomic-update-bundle")]
     pub struct AtomicUpdateBundle {
-        #[serde(rename = "bundle")]
+        #[serde(rename = "bundle", default)]
         pub bundles: Vec<BundleRef>,
     }
 
     #[derive(Serialize, Deserialize, Debug, Default)]
     #[serde(rename = "strict-identifier")]
     pub struct StrictIdentifier {
-        #[serde(rename = "bundle")]
+        #[serde(rename = "bundle", default)]
         pub bundles: Vec<BundleRef>,
     }
 
     #[derive(Serialize, Deserialize, Debug, Default)]
     #[serde(rename = "relocate")]
     pub struct Relocate {
-        #[serde(rename = "bundle")]
+        #[serde(rename = "bundle", default)]
         pub bundles: Vec<BundleRef>,
     }
 
     #[derive(Serialize, Deserialize, Debug, Default)]
     #[serde(rename = "scripts")]
     pub struct Scripts {
-        #[serde(rename = "preinstall")]
+        #[serde(rename = "preinstall", default)]
         pub pre_install: Vec<PreInstall>,
-        #[serde(rename = "postinstall")]
+        #[serde(rename = "postinstall", default)]
         pub post_install: Vec<PostInstall>,
     }
 
@@ -185,6 +192,12 @@ pub mod xml {
         pub choices: Vec<Choice>,
     }
 
+    impl Distribution {
+        pub fn read<R: Read>(reader: R) -> Result<Self, Error> {
+            from_reader(BufReader::new(reader)).map_err(Error::other)
+        }
+    }
+
     #[derive(Serialize, Deserialize, Debug)]
     #[serde(rename = "domains")]
     pub struct Domains {
@@ -0,0 +1,106 @@
+use std::io::Error;
+use std::io::Write;
+
+use quick_xml::se::to_writer;
+use serde::ser::SerializeStruct;
+use serde::Deserialize;
+use serde::Serialize;
+use serde::Serializer;
+
+/// https://learn.microsoft.com/en-us/uwp/schemas/appinstallerschema/schema-root
+#[derive(Deserialize, Debug)]
+#[serde(rename = "AppInstaller")]
+pub struct AppInstaller {
+    #[serde(rename = "@Version")]
+    pub version: String,
+    #[serde(rename = "@Uri")]
+    pub uri: String,
+    #[serde(rename = "MainPackage")]
+    pub main_package: MainPackage,
+    #[serde(rename = "UpdateSettings")]
+    pub update_settings: UpdateSettings,
+}
+
+impl AppInstaller {
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let mut s = String::new();
+        to_writer(&mut s, self).map_err(Error::other)?;
+        writer.write_all(r#"<?xml version="1.0" encoding="UTF-8"?>"#.as_bytes())?;
+        writer.write_all(s.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl Serialize for AppInstaller {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("AppInstaller", 5)?;
+        state.serialize_field(
+            "@xmlns",
+            "http://schemas.microsoft.com/appx/appinstaller/2018",
+        )?;
+        state.serialize_field("@Version", &self.version)?;
+        state.serialize_field("@Uri", &self.uri)?;
+        state.serialize_field("MainPackage", &self.main_package)?;
+        state.serialize_field("UpdateSettings", &self.update_settings)?;
+        state.end()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename = "MainPackage")]
+pub struct MainPackage {
+    #[serde(rename = "@Name")]
+    pub name: String,
+    #[serde(rename = "@Publisher")]
+    pub publisher: String,
+    #[serde(rename = "@Version")]
+    pub version: String,
+    #[serde(rename = "@ProcessorArchitecture")]
+    pub processor_architecture: String,
+    #[serde(rename = "@Uri")]
+    pub uri: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename = "UpdateSettings")]
+pub struct UpdateSettings {
+    #[serde(rename = "OnLaunch")]
+    pub on_launch: OnLaunch,
+    #[serde(
+        rename = "AutomaticBackgroundTask",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub automatic_background_task: Option<AutomaticBackgroundTask>,
+    #[serde(rename = "ForceUpdateFromAnyVersion", default)]
+    pub force_update_from_any_version: bool,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self {
+            on_launch: Default::default(),
+            automatic_background_task: Some(AutomaticBackgroundTask),
+            force_update_from_any_version: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename = "OnLaunch")]
+pub struct OnLaunch {
+    /// Valid values are 0–255. Zero means check on every launch.
+    #[serde(rename = "@HoursBetweenUpdateChecks")]
+    pub hours_between_update_checks: u8,
+    #[serde(rename = "@ShowPrompt")]
+    pub show_prompt: bool,
+    #[serde(rename = "@UpdateBlocksActivation")]
+    pub update_blocks_activation: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename = "AutomaticBackgroundTask")]
+pub struct AutomaticBackgroundTask;
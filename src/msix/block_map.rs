@@ -1,6 +1,9 @@
+use std::io::BufReader;
 use std::io::Error;
+use std::io::Read;
 use std::io::Write;
 
+use quick_xml::de::from_reader;
 use quick_xml::se::to_writer;
 use serde::ser::SerializeStruct;
 use serde::Deserialize;
@@ -18,6 +21,10 @@ pub struct BlockMap {
 }
 
 impl BlockMap {
+    pub fn read<R: Read>(reader: R) -> Result<Self, Error> {
+        from_reader(BufReader::new(reader)).map_err(Error::other)
+    }
+
     pub fn write<W: Write>(&self, mut writer: W) -> Result<(), Error> {
         let mut s = String::new();
         to_writer(&mut s, self).map_err(Error::other)?;
@@ -56,8 +63,10 @@ pub struct File {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename = "Block")]
 pub struct Block {
+    /// Base64-encoded hash of the uncompressed block.
     #[serde(rename = "@Hash")]
     pub hash: String,
-    #[serde(rename = "@Size")]
-    pub size: u64,
+    /// The size of the compressed block.
+    #[serde(rename = "@Size", default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
 }
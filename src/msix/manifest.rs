@@ -1,6 +1,9 @@
+use std::io::BufReader;
 use std::io::Error;
+use std::io::Read;
 use std::io::Write;
 
+use quick_xml::de::from_reader;
 use quick_xml::se::to_writer;
 use serde::ser::SerializeStruct;
 use serde::Deserialize;
@@ -24,6 +27,10 @@ pub struct Package {
 }
 
 impl Package {
+    pub fn read<R: Read>(reader: R) -> Result<Self, Error> {
+        from_reader(BufReader::new(reader)).map_err(Error::other)
+    }
+
     pub fn write<W: Write>(&self, mut writer: W) -> Result<(), Error> {
         let mut s = String::new();
         to_writer(&mut s, self).map_err(Error::other)?;
@@ -38,7 +45,7 @@ impl Serialize for Package {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("Package", 3)?;
+        let mut state = serializer.serialize_struct("Package", 10)?;
         state.serialize_field(
             "@xmlns",
             "http://schemas.microsoft.com/appx/manifest/foundation/windows10",
@@ -56,6 +63,11 @@ impl Serialize for Package {
             "http://schemas.microsoft.com/appx/manifest/uap/windows10/3",
         )?;
         state.serialize_field("@IgnorableNamespaces", "mp uap uap3")?;
+        state.serialize_field("Identity", &self.identity)?;
+        state.serialize_field("Properties", &self.properties)?;
+        state.serialize_field("Resources", &self.resources)?;
+        state.serialize_field("Dependencies", &self.dependencies)?;
+        state.serialize_field("Applications", &self.applications)?;
         state.end()
     }
 }
@@ -69,6 +81,12 @@ pub struct Identity {
     pub publisher: String,
     #[serde(rename = "@Version")]
     pub version: String,
+    #[serde(
+        rename = "@ProcessorArchitecture",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub processor_architecture: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -78,7 +96,7 @@ pub struct Properties {
     pub display_name: String,
     #[serde(rename = "PublisherDisplayName")]
     pub publisher_display_name: String,
-    #[serde(rename = "Decsription")]
+    #[serde(rename = "Description")]
     pub description: String,
     #[serde(rename = "Logo")]
     pub logo: String,
@@ -94,7 +112,7 @@ pub struct Resources {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename = "Resource")]
 pub struct Resource {
-    #[serde(rename = "Language")]
+    #[serde(rename = "@Language")]
     pub language: String,
 }
 
@@ -130,7 +148,8 @@ pub struct Application {
     pub id: String,
     #[serde(rename = "@Executable")]
     pub executable: String,
-    #[serde(rename = "uap:VisualElements")]
+    // The deserializer strips namespace prefixes.
+    #[serde(rename = "uap:VisualElements", alias = "VisualElements")]
     pub visual_elements: VisualElements,
 }
 
@@ -139,7 +158,7 @@ pub struct Application {
 pub struct VisualElements {
     #[serde(rename = "@DisplayName")]
     pub display_name: String,
-    #[serde(rename = "@Decsription")]
+    #[serde(rename = "@Description")]
     pub description: String,
     #[serde(rename = "@BackgroundColor")]
     pub background_color: String,
@@ -1,9 +1,17 @@
+mod app_installer;
 mod block_map;
 mod content_types;
 mod manifest;
 mod package;
+mod path;
+mod repository;
+
+pub use self::package::*;
+pub use self::path::*;
+pub use self::repository::*;
 
 pub mod xml {
+    pub use super::app_installer::*;
     pub use super::block_map::*;
     pub use super::content_types::*;
     pub use super::manifest::*;
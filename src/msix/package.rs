@@ -1,6 +1,8 @@
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::io::Error;
+use std::io::Read;
+use std::io::Seek;
 use std::io::Write;
 use std::path::Path;
 
@@ -10,8 +12,13 @@ use zip::read::ZipArchive;
 use zip::write::SimpleFileOptions;
 use zip::write::ZipWriter;
 
-use crate::hash::Sha256Reader;
+use crate::artifact::ArtifactFormat;
+use crate::artifact::ArtifactName;
+use crate::hash::Hasher;
+use crate::hash::Sha256;
+use crate::msix::is_footprint_file;
 use crate::msix::xml;
+use crate::msix::MemberNames;
 
 #[derive(Clone)]
 #[cfg_attr(test, derive(arbitrary::Arbitrary, PartialEq, Eq, Debug))]
@@ -22,9 +29,29 @@ pub struct Package {
     pub version: String,
     pub executable: String,
     pub logo: String,
+    /// `x86`, `x64`, `arm`, `arm64` or `neutral`.
+    ///
+    /// The manifest omits the architecture if it is not set, i.e. the package is `neutral`.
+    pub processor_architecture: Option<String>,
 }
 
 impl Package {
+    pub fn artifact_name(&self) -> ArtifactName {
+        ArtifactName {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            arch: self.processor_architecture.clone(),
+            format: ArtifactFormat::Msix,
+        }
+    }
+
+    /// Conventional package file name: `<name>_<version>_<arch>.msix`.
+    ///
+    /// The architecture is omitted for neutral packages.
+    pub fn file_name(&self) -> String {
+        self.artifact_name().to_string()
+    }
+
     pub fn write<P2: AsRef<Path>, P: AsRef<Path>>(
         &self,
         file: P2,
@@ -33,8 +60,10 @@ impl Package {
     ) -> Result<(), Error> {
         let file = file.as_ref();
         let directory = directory.as_ref();
-        let mut writer = ZipWriter::new(File::create(&file)?);
-        for entry in WalkDir::new(directory).into_iter() {
+        // Validate all the paths before creating the file.
+        let mut names = MemberNames::new();
+        let mut entries = Vec::new();
+        for entry in WalkDir::new(directory).sort_by_file_name().into_iter() {
             let entry = entry?;
             let entry_path = entry
                 .path()
@@ -44,12 +73,16 @@ impl Package {
             if entry_path == Path::new("") {
                 continue;
             }
-            let relative_path = Path::new(".").join(entry_path);
+            let name = names.insert(&entry_path)?;
+            entries.push((name, entry));
+        }
+        let mut writer = ZipWriter::new(File::create(&file)?);
+        for (name, entry) in entries.into_iter() {
             // TODO symlinks
             if entry.file_type().is_dir() {
-                writer.add_directory_from_path(relative_path, SimpleFileOptions::default())?;
+                writer.add_directory(name, SimpleFileOptions::default())?;
             } else {
-                writer.start_file_from_path(relative_path, SimpleFileOptions::default())?;
+                writer.start_file(name, SimpleFileOptions::default())?;
                 std::io::copy(&mut File::open(entry.path())?, writer.by_ref())?;
             }
         }
@@ -57,21 +90,19 @@ impl Package {
         let mut archive = ZipArchive::new(File::open(&file)?)?;
         let mut files = Vec::with_capacity(archive.len());
         for i in 0..archive.len() {
-            // TODO raw affects size or not ???
-            let mut file = archive.by_index_raw(i)?;
+            let mut file = archive.by_index(i)?;
             if file.is_dir() {
                 continue;
             }
-            let sha256_reader = Sha256Reader::new(&mut file);
-            let (hash, _) = sha256_reader.digest()?;
+            let blocks = block_hashes(&mut file)?
+                .into_iter()
+                .map(|hash| xml::Block { hash, size: None })
+                .collect();
             files.push(xml::File {
                 name: file.name().into(),
                 size: file.size(),
                 lfh_size: file.data_start() - file.header_start(),
-                blocks: vec![xml::Block {
-                    hash: hash.to_base64(),
-                    size: file.compressed_size(),
-                }],
+                blocks,
             });
         }
         drop(archive);
@@ -91,6 +122,7 @@ impl Package {
                 name: self.name.clone(),
                 publisher: self.publisher.clone(),
                 version: self.version.clone(),
+                processor_architecture: self.processor_architecture.clone(),
             },
             properties: xml::Properties {
                 display_name: self.name.clone(),
@@ -136,11 +168,114 @@ impl Package {
         writer.finish()?;
         Ok(())
     }
+
+    /// Read the package verifying the hashes of all the blocks listed in the block map.
+    ///
+    /// All the files except the footprint files have to be listed in the block map.
+    pub fn read<R: Read + Seek>(reader: R) -> Result<PackageContents, Error> {
+        let mut archive = ZipArchive::new(reader)?;
+        let manifest = xml::Package::read(archive.by_name("AppxManifest.xml")?)?;
+        let block_map = xml::BlockMap::read(archive.by_name("AppxBlockMap.xml")?)?;
+        for expected in block_map.files.iter() {
+            // Block map uses Windows path separator.
+            let name = expected.name.replace('\\', "/");
+            let mut file = archive.by_name(&name)?;
+            let lfh_size = file.data_start() - file.header_start();
+            if expected.size != file.size() || expected.lfh_size != lfh_size {
+                return Err(Error::other(format!("size mismatch: {}", name)));
+            }
+            let hashes = block_hashes(&mut file)?;
+            if hashes.len() != expected.blocks.len()
+                || hashes
+                    .iter()
+                    .zip(expected.blocks.iter())
+                    .any(|(actual, expected)| *actual != expected.hash)
+            {
+                return Err(Error::other(format!("block hash mismatch: {}", name)));
+            }
+        }
+        let mut files = Vec::new();
+        for name in archive.file_names() {
+            if name.ends_with('/') || is_footprint_file(name) {
+                continue;
+            }
+            if !block_map
+                .files
+                .iter()
+                .any(|file| file.name.replace('\\', "/") == name)
+            {
+                return Err(Error::other(format!("{} is not in the block map", name)));
+            }
+            files.push(name.to_string());
+        }
+        let package = Package {
+            name: manifest.identity.name.clone(),
+            description: manifest.properties.description.clone(),
+            publisher: manifest.identity.publisher.clone(),
+            version: manifest.identity.version.clone(),
+            executable: manifest
+                .applications
+                .applications
+                .first()
+                .map(|app| app.executable.clone())
+                .unwrap_or_default(),
+            logo: manifest.properties.logo.clone(),
+            processor_architecture: manifest.identity.processor_architecture.clone(),
+        };
+        Ok(PackageContents {
+            package,
+            manifest,
+            block_map,
+            files,
+        })
+    }
+}
+
+/// Package metadata and file list that [`Package::read`] returns.
+pub struct PackageContents {
+    pub package: Package,
+    pub manifest: xml::Package,
+    pub block_map: xml::BlockMap,
+    /// Package files excluding directories and footprint files.
+    pub files: Vec<String>,
 }
 
+/// Base64-encoded SHA-256 hashes of the uncompressed blocks of the file.
+fn block_hashes<R: Read>(mut reader: R) -> Result<Vec<String>, Error> {
+    let mut hashes = Vec::new();
+    let mut buf = vec![0_u8; BLOCK_LEN];
+    loop {
+        let n = read_block(reader.by_ref(), &mut buf[..])?;
+        if n == 0 {
+            break;
+        }
+        hashes.push(Sha256::compute(&buf[..n]).to_base64());
+        if n != BLOCK_LEN {
+            break;
+        }
+    }
+    Ok(hashes)
+}
+
+/// Read the whole block unless the end of the file is reached.
+fn read_block<R: Read>(mut reader: R, buf: &mut [u8]) -> Result<usize, Error> {
+    let mut n = 0;
+    while n != buf.len() {
+        match reader.read(&mut buf[n..])? {
+            0 => break,
+            m => n += m,
+        }
+    }
+    Ok(n)
+}
+
+const BLOCK_LEN: usize = 64 * 1024;
+
 #[cfg(test)]
 mod tests {
 
+    use std::fs::create_dir_all;
+    use std::fs::remove_file;
     use std::process::Command;
     use std::time::Duration;
 
@@ -148,9 +283,131 @@ mod tests {
     use tempfile::TempDir;
 
     use super::*;
+    use crate::msix::to_member_name;
     use crate::test::prevent_concurrency;
     use crate::test::DirectoryOfFiles;
 
+    #[test]
+    fn normalize_member_names() {
+        let workdir = TempDir::new().unwrap();
+        let directory = workdir.path().join("root");
+        let package_file = workdir.path().join("test.msix");
+        create_dir_all(directory.join("cafe\u{301}")).unwrap();
+        std::fs::write(directory.join("cafe\u{301}/re\u{301}sume\u{301}.txt"), "").unwrap();
+        let package = Package {
+            name: "test".into(),
+            description: "test".into(),
+            publisher: "test".into(),
+            version: "1.0.0.0".into(),
+            executable: "test.exe".into(),
+            logo: "logo.png".into(),
+            processor_architecture: None,
+        };
+        package.write(&package_file, &directory).unwrap();
+        let archive = ZipArchive::new(File::open(&package_file).unwrap()).unwrap();
+        let names: Vec<&str> = archive.file_names().collect();
+        assert!(names.contains(&"caf\u{e9}/"), "names = {:?}", names);
+        assert!(
+            names.contains(&"caf\u{e9}/r\u{e9}sum\u{e9}.txt"),
+            "names = {:?}",
+            names
+        );
+        // Canonically equivalent file names clash.
+        std::fs::write(directory.join("caf\u{e9}"), "").unwrap();
+        let error = package.write(&package_file, &directory).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidInput, error.kind());
+        // Windows-incompatible file names are rejected.
+        remove_file(directory.join("caf\u{e9}")).unwrap();
+        std::fs::write(directory.join("aux.txt"), "").unwrap();
+        let error = package.write(&package_file, &directory).unwrap_err();
+        assert_eq!(std::io::ErrorKind::InvalidInput, error.kind());
+    }
+
+    #[test]
+    fn write_read() {
+        let workdir = TempDir::new().unwrap();
+        let package_file = workdir.path().join("test.msix");
+        let package = Package {
+            name: "test".into(),
+            description: "test package".into(),
+            publisher: "CN=test".into(),
+            version: "1.2.3.4".into(),
+            executable: "bin\\test.exe".into(),
+            logo: "logo.png".into(),
+            processor_architecture: None,
+        };
+        arbtest(|u| {
+            let directory: DirectoryOfFiles = u.arbitrary()?;
+            let mut expected_files = Vec::new();
+            for entry in WalkDir::new(directory.path()).sort_by_file_name() {
+                let entry = entry.unwrap();
+                if entry.file_type().is_dir() {
+                    continue;
+                }
+                let path = entry.path().strip_prefix(directory.path()).unwrap();
+                match to_member_name(path) {
+                    Ok(name) => expected_files.push(name),
+                    Err(_) => {
+                        // Windows-reserved names, e.g. `con`, are rejected.
+                        assert!(package.write(&package_file, directory.path()).is_err());
+                        return Ok(());
+                    }
+                }
+            }
+            package.write(&package_file, directory.path()).unwrap();
+            let contents = Package::read(File::open(&package_file).unwrap()).unwrap();
+            assert_eq!(package, contents.package);
+            assert_eq!(expected_files, contents.files);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn block_hash_mismatch() {
+        let workdir = TempDir::new().unwrap();
+        let directory = workdir.path().join("root");
+        let package_file = workdir.path().join("test.msix");
+        create_dir_all(&directory).unwrap();
+        // Multiple blocks.
+        let data: Vec<u8> = (0..(BLOCK_LEN * 2 + 1)).map(|i| i as u8).collect();
+        std::fs::write(directory.join("file"), &data).unwrap();
+        let package = Package {
+            name: "test".into(),
+            description: "test".into(),
+            publisher: "test".into(),
+            version: "1.0.0.0".into(),
+            executable: "test.exe".into(),
+            logo: "logo.png".into(),
+            processor_architecture: None,
+        };
+        package.write(&package_file, &directory).unwrap();
+        let contents = Package::read(File::open(&package_file).unwrap()).unwrap();
+        assert_eq!(vec!["file".to_string()], contents.files);
+        assert_eq!(3, contents.block_map.files[0].blocks.len());
+        // Replace the file with the one that has the same size.
+        let mut archive = ZipArchive::new(File::open(&package_file).unwrap()).unwrap();
+        let tampered_file = workdir.path().join("tampered.msix");
+        let mut writer = ZipWriter::new(File::create(&tampered_file).unwrap());
+        for i in 0..archive.len() {
+            let file = archive.by_index(i).unwrap();
+            if file.name() == "file" {
+                drop(file);
+                writer
+                    .start_file("file", SimpleFileOptions::default())
+                    .unwrap();
+                let tampered: Vec<u8> = data.iter().map(|x| x.wrapping_add(1)).collect();
+                writer.write_all(&tampered).unwrap();
+            } else {
+                writer.raw_copy_file(file).unwrap();
+            }
+        }
+        writer.finish().unwrap();
+        let error = Package::read(File::open(&tampered_file).unwrap())
+            .err()
+            .unwrap();
+        assert_eq!("block hash mismatch: file", error.to_string());
+    }
+
     #[ignore]
     #[test]
     fn msixmgr_installs_random_package() {
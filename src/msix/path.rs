@@ -0,0 +1,298 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use unicode_normalization::is_nfc;
+use unicode_normalization::UnicodeNormalization;
+
+/// Convert file path into the name of the package member.
+///
+/// The name is NFC-normalized and uses `/` as the separator. Paths that can not be extracted on
+/// Windows are rejected.
+pub fn to_member_name(path: &Path) -> Result<String, PathError> {
+    let error = |kind| PathError {
+        path: path.to_path_buf(),
+        kind,
+    };
+    let mut components = Vec::new();
+    for component in path.components() {
+        let component = match component {
+            Component::Normal(component) => component,
+            Component::CurDir => continue,
+            Component::RootDir | Component::Prefix(..) => {
+                return Err(error(PathErrorKind::Absolute))
+            }
+            Component::ParentDir => return Err(error(PathErrorKind::ParentDir)),
+        };
+        let component = component
+            .to_str()
+            .ok_or_else(|| error(PathErrorKind::NonUtf8))?;
+        let component: String = component.nfc().collect();
+        validate_component(&component).map_err(error)?;
+        components.push(component);
+    }
+    if components.is_empty() {
+        return Err(error(PathErrorKind::Empty));
+    }
+    let name = components.join("/");
+    // Windows counts UTF-16 code units.
+    let len = name.encode_utf16().count();
+    if len > MAX_PATH_LEN {
+        return Err(error(PathErrorKind::TooLong(len)));
+    }
+    if is_footprint_file(&name) {
+        return Err(error(PathErrorKind::Reserved(name)));
+    }
+    Ok(name)
+}
+
+/// Detects names that refer to the same file on case-insensitive file systems.
+#[derive(Default)]
+pub struct MemberNames {
+    names: HashSet<String>,
+}
+
+impl MemberNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validate and normalize the path and check that it does not clash with the paths that were
+    /// added earlier.
+    pub fn insert(&mut self, path: &Path) -> Result<String, PathError> {
+        let name = to_member_name(path)?;
+        if !self.names.insert(name.to_lowercase()) {
+            return Err(PathError {
+                path: path.to_path_buf(),
+                kind: PathErrorKind::Duplicate(name),
+            });
+        }
+        Ok(name)
+    }
+}
+
+fn validate_component(component: &str) -> Result<(), PathErrorKind> {
+    debug_assert!(is_nfc(component));
+    if let Some(ch) = component
+        .chars()
+        .find(|ch| ch.is_control() || INVALID_CHARS.contains(ch))
+    {
+        return Err(PathErrorKind::InvalidChar(ch));
+    }
+    if component.ends_with(['.', ' ']) {
+        return Err(PathErrorKind::TrailingDotOrSpace(component.into()));
+    }
+    let len = component.encode_utf16().count();
+    if len > MAX_COMPONENT_LEN {
+        return Err(PathErrorKind::ComponentTooLong(len));
+    }
+    // `NUL.txt` is as bad as `NUL`.
+    let stem = match component.split_once('.') {
+        Some((stem, _)) => stem,
+        None => component,
+    };
+    let stem = stem.trim_end_matches(' ');
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+    {
+        return Err(PathErrorKind::Reserved(component.into()));
+    }
+    Ok(())
+}
+
+pub(crate) fn is_footprint_file(name: &str) -> bool {
+    FOOTPRINT_FILES
+        .iter()
+        .any(|file| file.eq_ignore_ascii_case(name))
+        || name
+            .split('/')
+            .next()
+            .is_some_and(|first| first.eq_ignore_ascii_case("AppxMetadata"))
+}
+
+#[derive(Debug)]
+pub struct PathError {
+    pub path: PathBuf,
+    pub kind: PathErrorKind,
+}
+
+impl Display for PathError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "invalid path {:?}: {}", self.path, self.kind)
+    }
+}
+
+impl std::error::Error for PathError {}
+
+impl From<PathError> for std::io::Error {
+    fn from(other: PathError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, other)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PathErrorKind {
+    Empty,
+    Absolute,
+    ParentDir,
+    NonUtf8,
+    InvalidChar(char),
+    TrailingDotOrSpace(String),
+    Reserved(String),
+    ComponentTooLong(usize),
+    TooLong(usize),
+    Duplicate(String),
+}
+
+impl Display for PathErrorKind {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Self::Empty => f.write_str("empty path"),
+            Self::Absolute => f.write_str("absolute paths are not allowed"),
+            Self::ParentDir => f.write_str("`..` is not allowed"),
+            Self::NonUtf8 => f.write_str("non utf-8 path"),
+            Self::InvalidChar(ch) => write!(f, "invalid character {:?}", ch),
+            Self::TrailingDotOrSpace(s) => {
+                write!(f, "file name ends with dot or space: {:?}", s)
+            }
+            Self::Reserved(s) => write!(f, "reserved name {:?}", s),
+            Self::ComponentTooLong(len) => write!(
+                f,
+                "file name is too long: {} > {} UTF-16 code units",
+                len, MAX_COMPONENT_LEN
+            ),
+            Self::TooLong(len) => write!(
+                f,
+                "path is too long: {} > {} UTF-16 code units",
+                len, MAX_PATH_LEN
+            ),
+            Self::Duplicate(s) => write!(f, "case-insensitive duplicate of {:?}", s),
+        }
+    }
+}
+
+/// `MAX_PATH` minus the terminating null character.
+const MAX_PATH_LEN: usize = 259;
+const MAX_COMPONENT_LEN: usize = 255;
+
+const INVALID_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Files that are generated by the package writer.
+const FOOTPRINT_FILES: [&str; 4] = [
+    "AppxManifest.xml",
+    "AppxBlockMap.xml",
+    "AppxSignature.p7x",
+    "[Content_Types].xml",
+];
+
+#[cfg(test)]
+mod tests {
+    use arbtest::arbtest;
+
+    use super::*;
+    use crate::test::Chars;
+    use crate::test::UNICODE;
+
+    #[test]
+    fn valid_names() {
+        assert_eq!(
+            "dir/file.txt",
+            to_member_name(Path::new("./dir/file.txt")).unwrap()
+        );
+        // NFD -> NFC
+        assert_eq!(
+            "caf\u{e9}/r\u{e9}sum\u{e9}",
+            to_member_name(Path::new("cafe\u{301}/re\u{301}sume\u{301}")).unwrap()
+        );
+        assert_eq!(
+            "\u{1f980}.txt",
+            to_member_name(Path::new("\u{1f980}.txt")).unwrap()
+        );
+        assert_eq!(
+            "CONSOLE.txt",
+            to_member_name(Path::new("CONSOLE.txt")).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_names() {
+        let kind = |path: &str| to_member_name(Path::new(path)).unwrap_err().kind;
+        assert_eq!(PathErrorKind::Empty, kind("."));
+        assert_eq!(PathErrorKind::Absolute, kind("/etc/passwd"));
+        assert_eq!(PathErrorKind::ParentDir, kind("../file"));
+        assert_eq!(PathErrorKind::InvalidChar(':'), kind("c:file"));
+        assert_eq!(PathErrorKind::InvalidChar('\\'), kind("dir\\file"));
+        assert_eq!(PathErrorKind::InvalidChar('\u{7}'), kind("bell\u{7}"));
+        assert_eq!(
+            PathErrorKind::TrailingDotOrSpace("file.".into()),
+            kind("dir/file.")
+        );
+        assert_eq!(
+            PathErrorKind::TrailingDotOrSpace("dir ".into()),
+            kind("dir /file")
+        );
+        assert_eq!(PathErrorKind::Reserved("nul.txt".into()), kind("nul.txt"));
+        assert_eq!(PathErrorKind::Reserved("Com1".into()), kind("dir/Com1"));
+        assert_eq!(
+            PathErrorKind::Reserved("appxmanifest.xml".into()),
+            kind("appxmanifest.xml")
+        );
+        assert_eq!(
+            PathErrorKind::Reserved("AppxMetadata/CodeIntegrity.cat".into()),
+            kind("AppxMetadata/CodeIntegrity.cat")
+        );
+        assert_eq!(PathErrorKind::ComponentTooLong(256), kind(&"x".repeat(256)));
+        let long_path = ["x".repeat(200), "y".repeat(100)].join("/");
+        assert_eq!(PathErrorKind::TooLong(301), kind(&long_path));
+        // Supplementary characters count as two UTF-16 code units.
+        assert_eq!(
+            PathErrorKind::ComponentTooLong(256),
+            kind(&"\u{1f980}".repeat(128))
+        );
+    }
+
+    #[test]
+    fn case_insensitive_duplicates() {
+        let mut names = MemberNames::new();
+        names.insert(Path::new("Dir/File")).unwrap();
+        names.insert(Path::new("Dir/Other")).unwrap();
+        let error = names.insert(Path::new("dir/file")).unwrap_err();
+        assert_eq!(PathErrorKind::Duplicate("dir/file".into()), error.kind);
+        names.insert(Path::new("caf\u{e9}")).unwrap();
+        let error = names.insert(Path::new("cafe\u{301}")).unwrap_err();
+        assert_eq!(PathErrorKind::Duplicate("caf\u{e9}".into()), error.kind);
+    }
+
+    #[test]
+    fn adversarial_names() {
+        let chars = Chars::from(UNICODE);
+        arbtest(|u| {
+            let len = u.int_in_range(1..=300)?;
+            let path = chars.arbitrary_string(u, len)?;
+            let Ok(name) = to_member_name(Path::new(&path)) else {
+                return Ok(());
+            };
+            assert!(is_nfc(&name), "name = {:?}", name);
+            assert!(name.encode_utf16().count() <= MAX_PATH_LEN);
+            for component in name.split('/') {
+                assert!(!component.is_empty(), "name = {:?}", name);
+                assert!(validate_component(component).is_ok(), "name = {:?}", name);
+            }
+            // Idempotence.
+            assert_eq!(name, to_member_name(Path::new(&name)).unwrap());
+            // Canonically equivalent paths map to the same name.
+            let nfd: String = name.nfd().collect();
+            assert_eq!(name, to_member_name(Path::new(&nfd)).unwrap());
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,607 @@
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::fs::create_dir_all;
+use std::fs::read_dir;
+use std::fs::remove_dir_all;
+use std::fs::File;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use crate::artifact::ArtifactFormat;
+use crate::artifact::ArtifactName;
+use crate::msix::xml;
+use crate::msix::Package;
+#[cfg(feature = "cosign")]
+use crate::sign::CosignSigner;
+
+/// Flat repository layout for `.appinstaller`-based updates.
+///
+/// Each package version is stored in its own directory, so that the URLs of the already published
+/// versions never change:
+///
+/// ```text
+/// <output-dir>/<name>_<arch>.appinstaller
+/// <output-dir>/<name>_<arch>.appinstaller.sig
+/// <output-dir>/<name>/<version>/<name>_<version>_<arch>.msix
+/// <output-dir>/<name>/<version>/.added
+/// ```
+///
+/// Neutral packages have no `_<arch>` suffix. Each architecture has its own `.appinstaller` file.
+pub struct Repository {
+    base_url: String,
+    retention: RetentionPolicy,
+    update_settings: xml::UpdateSettings,
+    #[cfg(feature = "cosign")]
+    cosign_signer: Option<CosignSigner>,
+}
+
+impl Repository {
+    pub fn new(base_url: String) -> Self {
+        Self {
+            base_url,
+            retention: Default::default(),
+            update_settings: Default::default(),
+            #[cfg(feature = "cosign")]
+            cosign_signer: None,
+        }
+    }
+
+    /// Retain at most `value` versions (including the latest one) of each package.
+    pub fn max_versions(&mut self, value: usize) {
+        self.retention.keep_last = value.max(1);
+    }
+
+    pub fn retention(&mut self, value: RetentionPolicy) {
+        self.retention = value;
+    }
+
+    pub fn update_settings(&mut self, value: xml::UpdateSettings) {
+        self.update_settings = value;
+    }
+
+    /// Sign `.appinstaller` files and the packages with cosign key.
+    ///
+    /// The signatures are written next to the files, e.g. `<name>_<arch>.appinstaller.sig` and
+    /// `<name>_<version>_<arch>.msix.sig`.
+    #[cfg(feature = "cosign")]
+    pub fn cosign_signer(&mut self, value: CosignSigner) {
+        self.cosign_signer = Some(value);
+    }
+
+    /// Add the package to the repository and point `.appinstaller` file to the latest version.
+    ///
+    /// The name, the version and the architecture are read from the package manifest. The latest
+    /// version is the highest one, not the one that was added last, i.e. adding an older version
+    /// does not downgrade the clients.
+    pub fn add<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        output_dir: P1,
+        package_file: P2,
+    ) -> Result<(), Error> {
+        let output_dir = output_dir.as_ref();
+        let package_file = package_file.as_ref();
+        let package = Package::read(File::open(package_file)?)?.package;
+        check_file_name("name", &package.name)?;
+        if let Some(arch) = package.processor_architecture.as_ref() {
+            check_file_name("architecture", arch)?;
+        }
+        parse_version(&package.version)
+            .ok_or_else(|| Error::other(format!("invalid msix version: {}", package.version)))?;
+        let file_name = package.file_name();
+        let package_dir = output_dir.join(&package.name);
+        let version_dir = package_dir.join(&package.version);
+        create_dir_all(&version_dir)?;
+        std::fs::copy(package_file, version_dir.join(&file_name))?;
+        #[cfg(feature = "cosign")]
+        if let Some(cosign_signer) = self.cosign_signer.as_ref() {
+            cosign_signer.sign_file(version_dir.join(&file_name))?;
+        }
+        if !version_dir.join(ADDED_FILE).exists() {
+            write_added_time(&version_dir, SystemTime::now())?;
+        }
+        self.remove_old_versions(&package_dir, Some(&package.version), false)?;
+        self.write_app_installer(
+            output_dir,
+            &package.name,
+            package.processor_architecture.as_deref(),
+        )
+    }
+
+    /// Generate `.appinstaller` file from the manifest of the highest version of the package that
+    /// was built for `arch`.
+    fn write_app_installer(
+        &self,
+        output_dir: &Path,
+        name: &str,
+        arch: Option<&str>,
+    ) -> Result<(), Error> {
+        let package_dir = output_dir.join(name);
+        let (version, file_name) = versions(&package_dir)?
+            .into_iter()
+            .find_map(|(_, version, version_dir)| {
+                let file_name = ArtifactName {
+                    name: name.into(),
+                    version: version.clone(),
+                    arch: arch.map(Into::into),
+                    format: ArtifactFormat::Msix,
+                }
+                .to_string();
+                version_dir
+                    .join(&file_name)
+                    .exists()
+                    .then_some((version, file_name))
+            })
+            .ok_or_else(|| Error::other(format!("no versions of {}", name)))?;
+        let latest = Package::read(File::open(package_dir.join(&version).join(&file_name))?)?;
+        let identity = latest.manifest.identity;
+        let app_installer_name = app_installer_file_name(name, arch);
+        let app_installer = xml::AppInstaller {
+            version: version.clone(),
+            uri: self.url([app_installer_name.as_str()]),
+            main_package: xml::MainPackage {
+                name: identity.name,
+                publisher: identity.publisher,
+                version: version.clone(),
+                processor_architecture: identity
+                    .processor_architecture
+                    .unwrap_or_else(|| "neutral".into()),
+                uri: self.url([name, version.as_str(), file_name.as_str()]),
+            },
+            update_settings: self.update_settings.clone(),
+        };
+        let app_installer_file = output_dir.join(&app_installer_name);
+        app_installer.write(File::create(&app_installer_file)?)?;
+        #[cfg(feature = "cosign")]
+        if let Some(cosign_signer) = self.cosign_signer.as_ref() {
+            cosign_signer.sign_file(&app_installer_file)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the versions of package `name` that are not retained by the policy.
+    ///
+    /// Returns the version directories that were removed or, if `dry_run` is set, that would be
+    /// removed. The highest version of each architecture is always retained. Since `.appinstaller`
+    /// files point to these versions, they stay valid.
+    pub fn gc<P: AsRef<Path>>(
+        &self,
+        output_dir: P,
+        name: &str,
+        dry_run: bool,
+    ) -> Result<Vec<PathBuf>, Error> {
+        self.remove_old_versions(&output_dir.as_ref().join(name), None, dry_run)
+    }
+
+    fn remove_old_versions(
+        &self,
+        package_dir: &Path,
+        current_version: Option<&str>,
+        dry_run: bool,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let versions = versions(package_dir)?;
+        let now = SystemTime::now();
+        let mut removed = Vec::new();
+        let mut latest_arches = BTreeSet::new();
+        for (i, (_, version, path)) in versions.into_iter().enumerate() {
+            let mut is_latest = false;
+            for arch in architectures(&path)? {
+                is_latest |= latest_arches.insert(arch);
+            }
+            if is_latest
+                || i < self.retention.keep_last.max(1)
+                || current_version == Some(version.as_str())
+                || self.retention.pinned.contains(&version)
+            {
+                continue;
+            }
+            if let Some(keep_days) = self.retention.keep_days {
+                match read_added_time(&path)? {
+                    Some(added) => {
+                        let age = now.duration_since(added).unwrap_or_default();
+                        if age < Duration::from_secs(keep_days * SECONDS_PER_DAY) {
+                            continue;
+                        }
+                    }
+                    None => log::warn!("unknown time when {} was added", path.display()),
+                }
+            }
+            if dry_run {
+                log::info!("would remove old version {}", path.display());
+            } else {
+                log::debug!("removing old version {}", path.display());
+                remove_dir_all(&path)?;
+            }
+            removed.push(path);
+        }
+        Ok(removed)
+    }
+
+    fn url<'a, I: IntoIterator<Item = &'a str>>(&self, components: I) -> String {
+        let mut url = self.base_url.trim_end_matches('/').to_string();
+        for comp in components.into_iter() {
+            url.push('/');
+            url.push_str(comp);
+        }
+        url
+    }
+}
+
+/// Record the time when the version was added to the repository.
+///
+/// Modification time of the directory is not used because it changes whenever the directory is
+/// touched.
+fn write_added_time(version_dir: &Path, time: SystemTime) -> Result<(), Error> {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_err(Error::other)?
+        .as_secs();
+    std::fs::write(version_dir.join(ADDED_FILE), secs.to_string())
+}
+
+fn read_added_time(version_dir: &Path) -> Result<Option<SystemTime>, Error> {
+    let s = match std::fs::read_to_string(version_dir.join(ADDED_FILE)) {
+        Ok(s) => s,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let secs: u64 = s.trim().parse().map_err(|_| {
+        Error::other(format!(
+            "invalid {} in {}",
+            ADDED_FILE,
+            version_dir.display()
+        ))
+    })?;
+    Ok(Some(UNIX_EPOCH + Duration::from_secs(secs)))
+}
+
+/// Versions of the package stored in `package_dir`, the highest version comes first.
+fn versions(package_dir: &Path) -> Result<Vec<([u16; 4], String, PathBuf)>, Error> {
+    let mut versions = Vec::new();
+    for entry in read_dir(package_dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let Some(name) = entry.file_name().to_str().map(ToString::to_string) else {
+            continue;
+        };
+        let Some(version) = parse_version(&name) else {
+            continue;
+        };
+        versions.push((version, name, entry.path()));
+    }
+    versions.sort_by_key(|(version, ..)| Reverse(*version));
+    Ok(versions)
+}
+
+/// Architectures of the packages stored in `version_dir`, `None` means neutral.
+fn architectures(version_dir: &Path) -> Result<Vec<Option<String>>, Error> {
+    let mut arches = Vec::new();
+    for entry in read_dir(version_dir)? {
+        let entry = entry?;
+        let Some(file_name) = entry.file_name().to_str().map(ToString::to_string) else {
+            continue;
+        };
+        if let Ok(artifact_name) = ArtifactName::parse(&file_name, ArtifactFormat::Msix) {
+            arches.push(artifact_name.arch);
+        }
+    }
+    Ok(arches)
+}
+
+/// `<name>_<arch>.appinstaller` or `<name>.appinstaller` for neutral packages.
+fn app_installer_file_name(name: &str, arch: Option<&str>) -> String {
+    match arch {
+        Some(arch) => format!("{}_{}.appinstaller", name, arch),
+        None => format!("{}.appinstaller", name),
+    }
+}
+
+/// Make sure that the value can be used as a file name component in the repository.
+fn check_file_name(what: &str, value: &str) -> Result<(), Error> {
+    let mut components = Path::new(value).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(x)), None) if x == value && !value.contains('_') => Ok(()),
+        _ => Err(Error::other(format!("invalid msix {}: {:?}", what, value))),
+    }
+}
+
+/// Parse `Major.Minor.Build.Revision` version.
+fn parse_version(s: &str) -> Option<[u16; 4]> {
+    let mut version = [0_u16; 4];
+    let mut iter = s.split('.');
+    for v in version.iter_mut() {
+        *v = iter.next()?.parse().ok()?;
+    }
+    if iter.next().is_some() {
+        return None;
+    }
+    Some(version)
+}
+
+/// Which versions of the package to keep in the repository.
+///
+/// The version is retained if any of the rules applies to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Keep this many latest versions (at least one).
+    pub keep_last: usize,
+    /// Keep versions that were added less than this many days ago.
+    pub keep_days: Option<u64>,
+    /// Never remove these versions.
+    pub pinned: BTreeSet<String>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            keep_last: DEFAULT_MAX_VERSIONS,
+            keep_days: None,
+            pinned: Default::default(),
+        }
+    }
+}
+
+const DEFAULT_MAX_VERSIONS: usize = 3;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+/// The file in the version directory that stores the time when the version was added (seconds
+/// since UNIX epoch).
+const ADDED_FILE: &str = ".added";
+
+#[cfg(test)]
+mod tests {
+    use arbtest::arbtest;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::test::DirectoryOfFiles;
+
+    #[test]
+    fn old_versions_are_removed() {
+        arbtest(|u| {
+            let workdir = TempDir::new().unwrap();
+            let output_dir = workdir.path().join("repo");
+            let package_file = workdir.path().join("test.msix");
+            let mut package: Package = u.arbitrary()?;
+            package.name = "test".into();
+            package.processor_architecture = None;
+            let directory: DirectoryOfFiles = u.arbitrary()?;
+            let mut repo = Repository::new("https://example.com/msix/".into());
+            repo.max_versions(2);
+            for version in ["1.0.0.0", "2.0.0.0", "10.0.0.0"] {
+                package.version = version.into();
+                package.write(&package_file, directory.path()).unwrap();
+                repo.add(&output_dir, &package_file).unwrap();
+            }
+            assert!(!output_dir.join("test/1.0.0.0").exists());
+            assert!(output_dir.join("test/2.0.0.0/test_2.0.0.0.msix").exists());
+            assert!(output_dir.join("test/10.0.0.0/test_10.0.0.0.msix").exists());
+            assert_latest_version(&output_dir, "10.0.0.0", None);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn older_version_does_not_downgrade_app_installer() {
+        arbtest(|u| {
+            let workdir = TempDir::new().unwrap();
+            let output_dir = workdir.path().join("repo");
+            let package_file = workdir.path().join("test.msix");
+            let mut package: Package = u.arbitrary()?;
+            package.name = "test".into();
+            package.processor_architecture = Some("x64".into());
+            let directory: DirectoryOfFiles = u.arbitrary()?;
+            let repo = Repository::new("https://example.com/msix/".into());
+            for version in ["10.0.0.0", "1.0.0.0"] {
+                package.version = version.into();
+                package.write(&package_file, directory.path()).unwrap();
+                repo.add(&output_dir, &package_file).unwrap();
+            }
+            assert!(output_dir
+                .join("test/1.0.0.0/test_1.0.0.0_x64.msix")
+                .exists());
+            assert_latest_version(&output_dir, "10.0.0.0", Some("x64"));
+            Ok(())
+        });
+    }
+
+    #[cfg(feature = "cosign")]
+    #[test]
+    fn cosign_app_installer() {
+        let (cosign_signer, cosign_verifier) = CosignSigner::generate();
+        let mut repo = Repository::new("https://example.com/msix/".into());
+        repo.cosign_signer(cosign_signer);
+        arbtest(|u| {
+            let workdir = TempDir::new().unwrap();
+            let output_dir = workdir.path().join("repo");
+            let package_file = workdir.path().join("test.msix");
+            let mut package: Package = u.arbitrary()?;
+            package.name = "test".into();
+            package.processor_architecture = None;
+            let directory: DirectoryOfFiles = u.arbitrary()?;
+            let app_installer_file = output_dir.join("test.appinstaller");
+            for version in ["1.0.0.0", "2.0.0.0"] {
+                package.version = version.into();
+                package.write(&package_file, directory.path()).unwrap();
+                repo.add(&output_dir, &package_file).unwrap();
+                assert!(output_dir.join("test.appinstaller.sig").exists());
+                cosign_verifier.verify_file(&app_installer_file).unwrap();
+                cosign_verifier
+                    .verify_file(output_dir.join(format!("test/{0}/test_{0}.msix", version)))
+                    .unwrap();
+            }
+            let mut app_installer = std::fs::read(&app_installer_file).unwrap();
+            app_installer.push(b'\n');
+            std::fs::write(&app_installer_file, app_installer).unwrap();
+            assert!(cosign_verifier.verify_file(&app_installer_file).is_err());
+            Ok(())
+        });
+    }
+
+    fn assert_latest_version(output_dir: &Path, version: &str, arch: Option<&str>) {
+        let app_installer =
+            std::fs::read_to_string(output_dir.join(app_installer_file_name("test", arch)))
+                .unwrap();
+        let uri = format!(
+            r#"Uri="https://example.com/msix/test/{0}/test_{0}{1}.msix""#,
+            version,
+            arch.map(|arch| format!("_{}", arch)).unwrap_or_default()
+        );
+        let arch = format!(r#"ProcessorArchitecture="{}""#, arch.unwrap_or("neutral"));
+        assert!(
+            app_installer.contains(&uri) && app_installer.contains(&arch),
+            "app installer = {}",
+            app_installer
+        );
+    }
+
+    #[test]
+    fn architectures_are_stored_side_by_side() {
+        arbtest(|u| {
+            let workdir = TempDir::new().unwrap();
+            let output_dir = workdir.path().join("repo");
+            let package_file = workdir.path().join("test.msix");
+            let mut package: Package = u.arbitrary()?;
+            package.name = "test".into();
+            let directory: DirectoryOfFiles = u.arbitrary()?;
+            let mut repo = Repository::new("https://example.com/msix/".into());
+            repo.max_versions(1);
+            for (version, arch) in [("1.0.0.0", "x64"), ("1.0.0.0", "arm64"), ("2.0.0.0", "x64")] {
+                package.version = version.into();
+                package.processor_architecture = Some(arch.into());
+                package.write(&package_file, directory.path()).unwrap();
+                repo.add(&output_dir, &package_file).unwrap();
+            }
+            assert!(output_dir
+                .join("test/1.0.0.0/test_1.0.0.0_arm64.msix")
+                .exists());
+            assert!(output_dir
+                .join("test/2.0.0.0/test_2.0.0.0_x64.msix")
+                .exists());
+            assert_latest_version(&output_dir, "2.0.0.0", Some("x64"));
+            assert_latest_version(&output_dir, "1.0.0.0", Some("arm64"));
+            // The only arm64 version is retained.
+            assert!(repo.gc(&output_dir, "test", false).unwrap().is_empty());
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        let workdir = TempDir::new().unwrap();
+        let output_dir = workdir.path().join("repo");
+        let package_file = workdir.path().join("test.msix");
+        let directory = workdir.path().join("rootfs");
+        create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("test.exe"), "").unwrap();
+        let repo = Repository::new("https://example.com/msix/".into());
+        for (name, arch) in [
+            ("..", None),
+            ("../test", None),
+            ("/test", None),
+            ("test/test", None),
+            ("test_test", None),
+            ("test", Some("../x64")),
+        ] {
+            let package = Package {
+                name: name.into(),
+                description: "test package".into(),
+                publisher: "CN=test".into(),
+                version: "1.0.0.0".into(),
+                executable: "test.exe".into(),
+                logo: "logo.png".into(),
+                processor_architecture: arch.map(Into::into),
+            };
+            package.write(&package_file, &directory).unwrap();
+            assert!(
+                repo.add(&output_dir, &package_file).is_err(),
+                "name = {:?}, arch = {:?}",
+                name,
+                arch
+            );
+        }
+        assert!(!workdir.path().join("test").exists());
+        assert!(!output_dir.exists());
+    }
+
+    #[test]
+    fn retention_policy() {
+        let workdir = TempDir::new().unwrap();
+        let output_dir = workdir.path().join("repo");
+        let now = SystemTime::now();
+        for (version, days) in [
+            ("1.0.0.0", 30),
+            ("2.0.0.0", 20),
+            ("3.0.0.0", 10),
+            ("4.0.0.0", 1),
+            ("5.0.0.0", 0),
+        ] {
+            let version_dir = output_dir.join("test").join(version);
+            create_dir_all(&version_dir).unwrap();
+            write_added_time(
+                &version_dir,
+                now - Duration::from_secs(days * SECONDS_PER_DAY),
+            )
+            .unwrap();
+        }
+        let mut repo = Repository::new("https://example.com/msix/".into());
+        repo.retention(RetentionPolicy {
+            keep_last: 1,
+            keep_days: Some(7),
+            pinned: ["1.0.0.0".to_string()].into(),
+        });
+        let expected = vec![
+            output_dir.join("test/3.0.0.0"),
+            output_dir.join("test/2.0.0.0"),
+        ];
+        assert_eq!(expected, repo.gc(&output_dir, "test", true).unwrap());
+        assert!(expected.iter().all(|path| path.exists()));
+        assert_eq!(expected, repo.gc(&output_dir, "test", false).unwrap());
+        assert!(expected.iter().all(|path| !path.exists()));
+        for version in ["1.0.0.0", "4.0.0.0", "5.0.0.0"] {
+            assert!(output_dir.join("test").join(version).exists());
+        }
+    }
+
+    #[test]
+    fn gc_keeps_app_installer_valid() {
+        arbtest(|u| {
+            let workdir = TempDir::new().unwrap();
+            let output_dir = workdir.path().join("repo");
+            let package_file = workdir.path().join("test.msix");
+            let mut package: Package = u.arbitrary()?;
+            package.name = "test".into();
+            package.processor_architecture = None;
+            let directory: DirectoryOfFiles = u.arbitrary()?;
+            let mut repo = Repository::new("https://example.com/msix/".into());
+            for version in ["10.0.0.0", "1.0.0.0"] {
+                package.version = version.into();
+                package.write(&package_file, directory.path()).unwrap();
+                repo.add(&output_dir, &package_file).unwrap();
+            }
+            repo.max_versions(1);
+            assert_eq!(
+                vec![output_dir.join("test/1.0.0.0")],
+                repo.gc(&output_dir, "test", false).unwrap()
+            );
+            assert!(output_dir.join("test/10.0.0.0/test_10.0.0.0.msix").exists());
+            assert_latest_version(&output_dir, "10.0.0.0", None);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn version_parse() {
+        assert_eq!(Some([1, 2, 3, 4]), parse_version("1.2.3.4"));
+        assert_eq!(None, parse_version("1.2.3"));
+        assert_eq!(None, parse_version("1.2.3.4.5"));
+        assert_eq!(None, parse_version("1.2.3.x"));
+        assert_eq!(None, parse_version("1.2.3.65536"));
+    }
+}
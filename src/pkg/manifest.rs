@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::fmt::Display;
@@ -8,6 +9,8 @@ use std::str::FromStr;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::artifact::ArtifactFormat;
+use crate::artifact::ArtifactName;
 use crate::deb::PackageName;
 use crate::deb::PackageVersion;
 
@@ -39,6 +42,17 @@ pub struct CompactManifest {
     pub annotations: HashMap<String, String>,
 }
 
+impl CompactManifest {
+    pub fn artifact_name(&self) -> ArtifactName {
+        ArtifactName {
+            name: self.name.to_string(),
+            version: self.version.to_string(),
+            arch: None,
+            format: ArtifactFormat::Pkg,
+        }
+    }
+}
+
 impl Display for CompactManifest {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         let s = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
@@ -64,9 +78,9 @@ pub struct Manifest {
     #[serde(flatten)]
     pub(crate) compact: CompactManifest,
     // TODO hashes
-    pub(crate) files: HashMap<PathBuf, String>,
+    pub(crate) files: BTreeMap<PathBuf, String>,
     pub(crate) config: Vec<PathBuf>,
-    pub(crate) directories: HashMap<PathBuf, String>,
+    pub(crate) directories: BTreeMap<PathBuf, String>,
 }
 
 impl Display for Manifest {
@@ -1,5 +1,4 @@
-use std::collections::HashMap;
-use std::collections::HashSet;
+use std::collections::BTreeMap;
 use std::fs::read_dir;
 use std::fs::File;
 use std::fs::Metadata;
@@ -14,6 +13,9 @@ use zstd::stream::read::Decoder as ZstdDecoder;
 use zstd::stream::write::Encoder as ZstdEncoder;
 
 use crate::archive::ArchiveWrite;
+use crate::archive::ExtendedMetadata;
+use crate::archive::FileOptions;
+use crate::archive::ModePolicy;
 use crate::archive::TarBuilder;
 use crate::hash::Sha256Reader;
 use crate::pkg::CompactManifest;
@@ -32,13 +34,30 @@ impl Package {
         }
     }
 
+    /// Conventional package file name: `<name>-<version>.pkg`.
+    pub fn file_name(&self) -> String {
+        self.manifest.artifact_name().to_string()
+    }
+
     pub fn write<W: Write>(&self, writer: W) -> Result<(), std::io::Error> {
+        self.write_with_mode_policy(writer, &Default::default())
+    }
+
+    /// Write the package normalizing file modes according to `mode_policy`.
+    pub fn write_with_mode_policy<W: Write>(
+        &self,
+        writer: W,
+        mode_policy: &ModePolicy,
+    ) -> Result<(), std::io::Error> {
         let mut package = TarBuilder::new(ZstdEncoder::new(writer, COMPRESSION_LEVEL)?);
-        let mut files: HashMap<PathBuf, String> = HashMap::new();
-        let mut config: HashSet<PathBuf> = HashSet::new();
-        let mut directories: HashMap<PathBuf, String> = HashMap::new();
-        let mut file_contents: HashMap<PathBuf, (Metadata, Vec<u8>)> = HashMap::new();
-        for entry in WalkDir::new(self.directory.as_path()).into_iter() {
+        let mut files: BTreeMap<PathBuf, String> = BTreeMap::new();
+        let mut config: Vec<PathBuf> = Vec::new();
+        let mut directories: BTreeMap<PathBuf, String> = BTreeMap::new();
+        let mut file_contents: Vec<(PathBuf, Metadata, FileOptions, Vec<u8>)> = Vec::new();
+        for entry in WalkDir::new(self.directory.as_path())
+            .sort_by_file_name()
+            .into_iter()
+        {
             let entry = entry?;
             let path = entry
                 .path()
@@ -49,20 +68,24 @@ impl Package {
             if absolute_path == Path::new("/") {
                 continue;
             }
-            eprintln!("path {:?}", absolute_path.display());
+            log::debug!("path {:?}", absolute_path.display());
             if entry.file_type().is_dir() {
                 if read_dir(entry.path())?.count() == 0 {
                     directories.insert(absolute_path.clone(), "y".to_string());
                 }
                 if absolute_path.starts_with(Path::new("/etc")) {
-                    config.insert(absolute_path);
+                    config.push(absolute_path);
                 }
             } else {
                 let mut reader = Sha256Reader::new(File::open(entry.path())?);
                 let mut contents = Vec::new();
                 reader.read_to_end(&mut contents)?;
                 let metadata = std::fs::metadata(entry.path())?;
-                file_contents.insert(absolute_path.clone(), (metadata, contents));
+                let options = FileOptions {
+                    mode: Some(mode_policy.mode(&absolute_path, &metadata)),
+                    extended_metadata: ExtendedMetadata::read(entry.path(), &metadata)?,
+                };
+                file_contents.push((absolute_path.clone(), metadata, options, contents));
                 let (sha256, _) = reader.digest()?;
                 files.insert(absolute_path, format!("1${}", sha256));
             }
@@ -71,13 +94,13 @@ impl Package {
         let manifest = Manifest {
             compact: self.manifest.clone(),
             files,
-            config: config.into_iter().collect(),
+            config,
             directories,
         };
         package.add_regular_file("+MANIFEST", manifest.to_string())?;
-        for (path, (metadata, contents)) in file_contents.into_iter() {
-            eprintln!("file path {:?}", path.display());
-            package.add_regular_file_with_metadata(path, &metadata, contents)?;
+        for (path, metadata, options, contents) in file_contents.into_iter() {
+            log::debug!("file path {:?}", path.display());
+            package.add_regular_file_with_metadata(path, &metadata, &options, contents)?;
         }
         package.into_inner()?.finish()?;
         Ok(())
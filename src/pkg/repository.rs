@@ -20,6 +20,8 @@ use crate::hash::Sha256Reader;
 use crate::pkg::Package;
 use crate::pkg::PackageMeta;
 use crate::pkg::SigningKey;
+#[cfg(feature = "cosign")]
+use crate::sign::CosignSigner;
 
 pub struct Repository {
     packages: Vec<PackageMeta>,
@@ -33,7 +35,7 @@ impl Repository {
     {
         let mut packages = Vec::new();
         let mut push_package = |directory: &Path, path: &Path| -> Result<(), std::io::Error> {
-            eprintln!("reading {}", path.display());
+            log::info!("reading {}", path.display());
             let relative_path = Path::new(".").join(
                 path.strip_prefix(directory)
                     .map_err(std::io::Error::other)?
@@ -41,6 +43,7 @@ impl Repository {
             );
             let mut reader = Sha256Reader::new(File::open(path)?);
             let compact = Package::read_compact_manifest(reader.by_ref())?;
+            compact.artifact_name().check_path(path);
             let (sha256, size) = reader.digest()?;
             let meta = PackageMeta {
                 compact,
@@ -55,7 +58,7 @@ impl Repository {
         for path in paths.into_iter() {
             let path = path.as_ref();
             if path.is_dir() {
-                for entry in WalkDir::new(path).into_iter() {
+                for entry in WalkDir::new(path).sort_by_file_name().into_iter() {
                     let entry = entry?;
                     if entry.file_type().is_dir() || !is_package(entry.path()) {
                         continue;
@@ -111,6 +114,39 @@ impl Repository {
         Ok(())
     }
 
+    /// Build the repository and additionally sign the metadata files and the packages with cosign
+    /// key.
+    ///
+    /// The signatures are written to `<file>.sig`, e.g. `packagesite.pkg.sig`.
+    #[cfg(feature = "cosign")]
+    pub fn build_with_cosign<P: AsRef<Path>>(
+        self,
+        output_dir: P,
+        signing_key: &SigningKey,
+        cosign_signer: &CosignSigner,
+    ) -> Result<(), std::io::Error> {
+        let output_dir = output_dir.as_ref();
+        let package_files: Vec<PathBuf> = self
+            .packages
+            .iter()
+            .map(|meta| output_dir.join(&meta.repopath))
+            .collect();
+        self.build(output_dir, signing_key)?;
+        for file_name in ["meta.conf", "meta.txz", "packagesite.pkg", "data.pkg"] {
+            cosign_signer.sign_file(output_dir.join(file_name))?;
+        }
+        symlink("meta.conf.sig", output_dir.join("meta.sig"))?;
+        symlink(
+            "packagesite.pkg.sig",
+            output_dir.join("packagesite.txz.sig"),
+        )?;
+        symlink("data.pkg.sig", output_dir.join("data.txz.sig"))?;
+        for package_file in package_files.iter() {
+            cosign_signer.sign_file(package_file)?;
+        }
+        Ok(())
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = &PackageMeta> {
         self.packages.iter()
     }
@@ -346,6 +382,44 @@ mod tests {
         });
     }
 
+    #[cfg(feature = "cosign")]
+    #[test]
+    fn cosign_packagesite() {
+        let (signing_key, _verifying_key) = SigningKey::generate();
+        let (cosign_signer, cosign_verifier) = CosignSigner::generate();
+        arbtest(|u| {
+            let workdir = TempDir::new().unwrap();
+            let package_file = workdir.path().join("test.pkg");
+            let package: CompactManifest = u.arbitrary()?;
+            let directory: DirectoryOfFiles = u.arbitrary()?;
+            Package::new(package, directory.path().into())
+                .write(File::create(package_file.as_path()).unwrap())
+                .unwrap();
+            Repository::new([workdir.path()])
+                .unwrap()
+                .build_with_cosign(workdir.path(), &signing_key, &cosign_signer)
+                .unwrap();
+            for file_name in [
+                "meta",
+                "meta.txz",
+                "packagesite.pkg",
+                "packagesite.txz",
+                "data.txz",
+                "test.pkg",
+            ] {
+                cosign_verifier
+                    .verify_file(workdir.path().join(file_name))
+                    .unwrap();
+            }
+            let packagesite_file = workdir.path().join("packagesite.pkg");
+            let mut packagesite = std::fs::read(&packagesite_file).unwrap();
+            packagesite.push(0);
+            std::fs::write(&packagesite_file, packagesite).unwrap();
+            assert!(cosign_verifier.verify_file(&packagesite_file).is_err());
+            Ok(())
+        });
+    }
+
     #[ignore]
     #[test]
     fn freebsd_pkg_adds_repo() {
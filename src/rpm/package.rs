@@ -16,6 +16,10 @@ use walkdir::WalkDir;
 //use zstd::stream::write::Encoder as ZstdEncoder;
 use crate::archive::ArchiveWrite;
 use crate::archive::CpioBuilder;
+use crate::archive::ModePolicy;
+use crate::archive::FILE_TYPE_MASK;
+use crate::artifact::ArtifactFormat;
+use crate::artifact::ArtifactName;
 use crate::compress::AnyDecoder;
 use crate::hash::Hasher;
 use crate::hash::Sha256Hash;
@@ -47,11 +51,35 @@ pub struct Package {
 }
 
 impl Package {
-    pub fn write<W, P>(
+    pub fn artifact_name(&self) -> ArtifactName {
+        ArtifactName {
+            name: self.name.clone(),
+            version: format!("{}-{}", self.version, RELEASE),
+            arch: Some(self.arch.clone()),
+            format: ArtifactFormat::Rpm,
+        }
+    }
+
+    /// Conventional package file name: `<name>-<version>-<release>.<arch>.rpm`.
+    pub fn file_name(&self) -> String {
+        self.artifact_name().to_string()
+    }
+
+    pub fn write<W, P>(self, writer: W, directory: P, signer: &PackageSigner) -> Result<(), Error>
+    where
+        W: Write,
+        P: AsRef<Path>,
+    {
+        self.write_with_mode_policy(writer, directory, signer, &Default::default())
+    }
+
+    /// Write the package normalizing file modes according to `mode_policy`.
+    pub fn write_with_mode_policy<W, P>(
         self,
         mut writer: W,
         directory: P,
         signer: &PackageSigner,
+        mode_policy: &ModePolicy,
     ) -> Result<(), Error>
     where
         // TODO + Seek
@@ -59,7 +87,7 @@ impl Package {
         P: AsRef<Path>,
     {
         let lead = Lead::new(CString::new(self.name.clone()).unwrap());
-        eprintln!("write {lead:?}");
+        log::debug!("write {lead:?}");
         lead.write(writer.by_ref())?;
         let mut basenames = Vec::<CString>::new();
         let mut dirnames = Vec::<CString>::new();
@@ -70,7 +98,7 @@ impl Package {
         let mut filemodes = Vec::<u16>::new();
         let mut filesizes = Vec::<u32>::new();
         // TODO do not repeat walkdir in from_directory
-        for entry in WalkDir::new(&directory).into_iter() {
+        for entry in WalkDir::new(&directory).sort_by_file_name().into_iter() {
             let entry = entry?;
             //let meta = entry.metadata()?;
             let path = entry.path();
@@ -83,8 +111,9 @@ impl Package {
                 continue;
             }
             //let entry_path = Path::new(".").join(entry_path);
-            let entry_path = Path::new("/tmp/rpm").join(entry_path);
             let meta = entry.metadata()?;
+            let mode = (meta.mode() & FILE_TYPE_MASK) | mode_policy.mode(&entry_path, &meta);
+            let entry_path = Path::new("/tmp/rpm").join(entry_path);
             if let (Some(file_name), Some(parent)) = (
                 entry_path.file_name().and_then(|x| x.to_str()),
                 entry_path.parent().and_then(|x| x.to_str()),
@@ -100,7 +129,7 @@ impl Package {
                 dirindices.push(i as u32);
                 usernames.push(c"root".into());
                 groupnames.push(c"root".into());
-                filemodes.push(meta.mode() as u16);
+                filemodes.push(mode as u16);
                 filesizes.push(meta.size() as u32);
                 let hash = if path.is_dir() {
                     String::new()
@@ -121,8 +150,9 @@ impl Package {
         header2.insert(Entry::FileModes(filemodes.try_into()?));
         header2.insert(Entry::FileSizes(filesizes.try_into()?));
         let mut payload = Vec::new();
-        CpioBuilder::from_directory(
+        CpioBuilder::from_directory_with_mode_policy(
             directory,
+            mode_policy,
             GzEncoder::new(&mut payload, Compression::best()),
             // TODO
             //ZstdEncoder::new(&mut payload, COMPRESSION_LEVEL)?,
@@ -145,7 +175,7 @@ impl Package {
             .sign(&header2)
             .map_err(|_| Error::other("failed to sign rpm"))?
             .to_binary()?;
-        eprintln!("header2 len {}", header2.len());
+        log::debug!("header2 len {}", header2.len());
         let header1 = Header::new(
             Signatures {
                 signature_v3,
@@ -199,7 +229,7 @@ impl Package {
             version: xml::Version {
                 epoch: 0,
                 version: self.version,
-                release: "1".into(),
+                release: RELEASE.into(),
             },
             checksum: xml::Checksum {
                 kind: "sha256".into(),
@@ -323,6 +353,9 @@ impl From<Signatures> for HashMap<SignatureTag, SignatureEntry> {
 
 const _COMPRESSION_LEVEL: i32 = 22;
 
+/// Package release number.
+const RELEASE: &str = "1";
+
 #[cfg(test)]
 mod tests {
     use std::fs::File;
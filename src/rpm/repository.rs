@@ -23,6 +23,8 @@ use crate::hash::Hasher;
 use crate::hash::Sha256Hash;
 use crate::rpm::Package;
 use crate::rpm::PackageSigner;
+#[cfg(feature = "cosign")]
+use crate::sign::CosignSigner;
 
 pub struct Repository {
     packages: HashMap<PathBuf, (Package, Sha256Hash, Vec<PathBuf>)>,
@@ -36,7 +38,7 @@ impl Repository {
     {
         let mut packages = HashMap::new();
         let mut push_package = |directory: &Path, path: &Path| -> Result<(), std::io::Error> {
-            eprintln!("reading {}", path.display());
+            log::info!("reading {}", path.display());
             let relative_path = Path::new(".").join(
                 path.strip_prefix(directory)
                     .map_err(std::io::Error::other)?
@@ -44,13 +46,14 @@ impl Repository {
             );
             let reader = File::open(path)?;
             let package = Package::read(reader)?;
+            package.0.artifact_name().check_path(path);
             packages.insert(relative_path, package);
             Ok(())
         };
         for path in paths.into_iter() {
             let path = path.as_ref();
             if path.is_dir() {
-                for entry in WalkDir::new(path).into_iter() {
+                for entry in WalkDir::new(path).sort_by_file_name().into_iter() {
                     let entry = entry?;
                     if entry.file_type().is_dir()
                         || entry.path().extension() != Some(OsStr::new("rpm"))
@@ -113,6 +116,31 @@ impl Repository {
         signature.write_armored(File::create(repodata.join("repomd.xml.asc"))?)?;
         Ok(())
     }
+
+    /// Write the repository and additionally sign `repomd.xml` file and the packages with cosign
+    /// key.
+    ///
+    /// The signatures are written to `repomd.xml.sig` and `<package>.rpm.sig`.
+    #[cfg(feature = "cosign")]
+    pub fn write_with_cosign<P: AsRef<Path>>(
+        self,
+        output_dir: P,
+        signer: &PackageSigner,
+        cosign_signer: &CosignSigner,
+    ) -> Result<(), Error> {
+        let output_dir = output_dir.as_ref();
+        let package_files: Vec<PathBuf> = self
+            .packages
+            .keys()
+            .map(|path| output_dir.join(path))
+            .collect();
+        self.write(output_dir, signer)?;
+        cosign_signer.sign_file(output_dir.join("repodata").join("repomd.xml"))?;
+        for package_file in package_files.iter() {
+            cosign_signer.sign_file(package_file)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -487,6 +515,49 @@ mod tests {
         let _otherdata = OtherData::from_str(&input).unwrap();
     }
 
+    #[cfg(feature = "cosign")]
+    #[test]
+    fn cosign_repo_md() {
+        let (signing_key, _verifying_key) = SigningKey::generate("wolfpack".into()).unwrap();
+        let signer = PackageSigner::new(signing_key);
+        let (cosign_signer, cosign_verifier) = CosignSigner::generate();
+        arbtest(|u| {
+            let workdir = TempDir::new().unwrap();
+            let package_file = workdir.path().join("test.rpm");
+            let package = Package {
+                name: "test".into(),
+                version: "1.0.0".into(),
+                summary: "test".into(),
+                description: "test".into(),
+                license: "MIT".into(),
+                url: "https://example.com/".into(),
+                arch: "x86_64".into(),
+            };
+            let directory: DirectoryOfFiles = u.arbitrary()?;
+            package
+                .write(
+                    &mut File::create(package_file.as_path()).unwrap(),
+                    directory.path(),
+                    &signer,
+                )
+                .unwrap();
+            let repository = Repository::new([workdir.path()]).unwrap();
+            repository
+                .write_with_cosign(workdir.path(), &signer, &cosign_signer)
+                .unwrap();
+            let repo_md_file = workdir.path().join("repodata/repomd.xml");
+            assert!(workdir.path().join("repodata/repomd.xml.sig").exists());
+            cosign_verifier.verify_file(&repo_md_file).unwrap();
+            let mut repo_md = std::fs::read(&repo_md_file).unwrap();
+            repo_md.push(b'\n');
+            std::fs::write(&repo_md_file, repo_md).unwrap();
+            assert!(cosign_verifier.verify_file(&repo_md_file).is_err());
+            cosign_verifier.verify_file(&package_file).unwrap();
+            Ok(())
+        })
+        .budget(Duration::from_secs(5));
+    }
+
     #[ignore]
     #[test]
     fn dnf_install() {
@@ -0,0 +1,251 @@
+use std::ffi::OsString;
+use std::path::Path;
+use std::path::PathBuf;
+
+use base64ct::Base64;
+use base64ct::Encoding;
+use p256::ecdsa::signature::Signer as _;
+use p256::ecdsa::signature::Verifier as _;
+use p256::ecdsa::Signature;
+use p256::pkcs8::DecodePrivateKey;
+use p256::pkcs8::DecodePublicKey;
+use p256::pkcs8::EncodePrivateKey;
+use p256::pkcs8::EncodePublicKey;
+use p256::pkcs8::LineEnding;
+use rand::rngs::OsRng;
+
+use crate::sign::Error;
+use crate::sign::Signer;
+use crate::sign::Verifier;
+
+/// Key-based sigstore signer.
+///
+/// Produces ECDSA P-256 SHA-256 signatures that `cosign verify-blob --key` accepts.
+pub struct CosignSigner(p256::ecdsa::SigningKey);
+
+impl CosignSigner {
+    pub fn generate() -> (Self, CosignVerifier) {
+        let signing_key = p256::ecdsa::SigningKey::random(&mut OsRng);
+        let verifying_key = *signing_key.verifying_key();
+        (Self(signing_key), CosignVerifier(verifying_key))
+    }
+
+    pub fn verifying_key(&self) -> CosignVerifier {
+        CosignVerifier(*self.0.verifying_key())
+    }
+
+    /// Read unencrypted PKCS#8 PEM private key.
+    pub fn from_pem(pem: &str) -> Result<Self, std::io::Error> {
+        p256::ecdsa::SigningKey::from_pkcs8_pem(pem)
+            .map(Self)
+            .map_err(|e| key_format_error("PKCS#8 PEM private key", e.to_string()))
+    }
+
+    /// Read unencrypted PKCS#8 private key either in PEM or in DER format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, std::io::Error> {
+        let result = match pem_str(bytes) {
+            Some(pem) => p256::ecdsa::SigningKey::from_pkcs8_pem(pem).map_err(|e| e.to_string()),
+            None => p256::ecdsa::SigningKey::from_pkcs8_der(bytes).map_err(|e| e.to_string()),
+        };
+        result
+            .map(Self)
+            .map_err(|e| key_format_error("PKCS#8 PEM or DER private key", e))
+    }
+
+    pub fn to_pem(&self) -> Result<String, Error> {
+        let pem = self.0.to_pkcs8_pem(LineEnding::LF).map_err(|_| Error)?;
+        Ok(pem.as_str().to_string())
+    }
+
+    /// Sign the file and write base64-encoded signature to `<file>.sig`.
+    ///
+    /// Returns the path of the signature file.
+    pub fn sign_file<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf, std::io::Error> {
+        let path = path.as_ref();
+        let message = std::fs::read(path)?;
+        let signature = self
+            .sign(&message)
+            .map_err(|_| std::io::Error::other(format!("failed to sign {}", path.display())))?;
+        let signature_file = signature_path(path);
+        std::fs::write(&signature_file, Base64::encode_string(&signature))?;
+        Ok(signature_file)
+    }
+}
+
+impl Signer for CosignSigner {
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        let signature: Signature = self.0.try_sign(message).map_err(|_| Error)?;
+        Ok(signature.to_der().as_bytes().to_vec())
+    }
+}
+
+pub struct CosignVerifier(p256::ecdsa::VerifyingKey);
+
+impl CosignVerifier {
+    /// Read PEM public key (e.g. `cosign.pub`).
+    pub fn from_pem(pem: &str) -> Result<Self, std::io::Error> {
+        p256::ecdsa::VerifyingKey::from_public_key_pem(pem)
+            .map(Self)
+            .map_err(|e| key_format_error("PEM public key", e.to_string()))
+    }
+
+    /// Read SubjectPublicKeyInfo public key either in PEM or in DER format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, std::io::Error> {
+        let result = match pem_str(bytes) {
+            Some(pem) => {
+                p256::ecdsa::VerifyingKey::from_public_key_pem(pem).map_err(|e| e.to_string())
+            }
+            None => {
+                p256::ecdsa::VerifyingKey::from_public_key_der(bytes).map_err(|e| e.to_string())
+            }
+        };
+        result
+            .map(Self)
+            .map_err(|e| key_format_error("PEM or DER public key", e))
+    }
+
+    pub fn to_pem(&self) -> Result<String, Error> {
+        self.0.to_public_key_pem(LineEnding::LF).map_err(|_| Error)
+    }
+
+    /// Verify the file using base64-encoded signature from `<file>.sig`.
+    pub fn verify_file<P: AsRef<Path>>(&self, path: P) -> Result<(), std::io::Error> {
+        let path = path.as_ref();
+        let message = std::fs::read(path)?;
+        let signature_file = signature_path(path);
+        let signature = std::fs::read_to_string(&signature_file)?;
+        let signature = Base64::decode_vec(signature.trim()).map_err(std::io::Error::other)?;
+        self.verify(&message, &signature).map_err(|_| {
+            std::io::Error::other(format!("signature verification failed: {}", path.display()))
+        })
+    }
+}
+
+impl Verifier for CosignVerifier {
+    fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), Error> {
+        let signature = Signature::from_der(signature).map_err(|_| Error)?;
+        self.0.verify(message, &signature).map_err(|_| Error)
+    }
+}
+
+/// Signature file path for the signed file.
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut file_name: OsString = path.as_os_str().into();
+    file_name.push(".sig");
+    file_name.into()
+}
+
+/// Returns the key as a string if it is PEM-encoded.
+fn pem_str(bytes: &[u8]) -> Option<&str> {
+    let s = std::str::from_utf8(bytes).ok()?;
+    s.trim_start().starts_with("-----BEGIN ").then_some(s)
+}
+
+fn key_format_error(expected: &str, error: String) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("expected P-256 {}: {}", expected, error),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use arbtest::arbtest;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn read_pem_and_der_keys() {
+        let (signer, verifier) = CosignSigner::generate();
+        let der = signer.0.to_pkcs8_der().unwrap();
+        let pem = signer.to_pem().unwrap();
+        for bytes in [der.as_bytes(), pem.as_bytes()] {
+            let actual = CosignSigner::from_bytes(bytes).unwrap();
+            assert_eq!(signer.0, actual.0);
+        }
+        let der = verifier.0.to_public_key_der().unwrap();
+        let pem = verifier.to_pem().unwrap();
+        for bytes in [der.as_bytes(), pem.as_bytes()] {
+            let actual = CosignVerifier::from_bytes(bytes).unwrap();
+            assert_eq!(verifier.0, actual.0);
+        }
+        let error = CosignVerifier::from_bytes(b"not a key").err().unwrap();
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+        assert!(
+            error
+                .to_string()
+                .starts_with("expected P-256 PEM or DER public key"),
+            "error = {}",
+            error
+        );
+        // Public key is not a private key.
+        assert!(CosignSigner::from_bytes(verifier.to_pem().unwrap().as_bytes()).is_err());
+        let error = CosignSigner::from_pem(&verifier.to_pem().unwrap())
+            .err()
+            .unwrap();
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+        assert!(
+            error
+                .to_string()
+                .starts_with("expected P-256 PKCS#8 PEM private key"),
+            "error = {}",
+            error
+        );
+        let error = CosignVerifier::from_pem("not a key").err().unwrap();
+        assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+        assert!(
+            error
+                .to_string()
+                .starts_with("expected P-256 PEM public key"),
+            "error = {}",
+            error
+        );
+    }
+
+    #[test]
+    fn sign_verify_file() {
+        let (signer, verifier) = CosignSigner::generate();
+        let signer = CosignSigner::from_pem(&signer.to_pem().unwrap()).unwrap();
+        let verifier = CosignVerifier::from_pem(&verifier.to_pem().unwrap()).unwrap();
+        arbtest(|u| {
+            let workdir = TempDir::new().unwrap();
+            let path = workdir.path().join("Release");
+            let message: Vec<u8> = u.arbitrary()?;
+            std::fs::write(&path, &message).unwrap();
+            let signature_file = signer.sign_file(&path).unwrap();
+            assert_eq!(workdir.path().join("Release.sig"), signature_file);
+            verifier.verify_file(&path).unwrap();
+            let mut other_message = message.clone();
+            other_message.push(0);
+            std::fs::write(&path, &other_message).unwrap();
+            assert!(verifier.verify_file(&path).is_err());
+            Ok(())
+        });
+    }
+
+    #[ignore]
+    #[test]
+    fn cosign_verifies_blob() {
+        let (signer, verifier) = CosignSigner::generate();
+        let workdir = TempDir::new().unwrap();
+        let path = workdir.path().join("Release");
+        let verifying_key_file = workdir.path().join("cosign.pub");
+        std::fs::write(&path, "hello world").unwrap();
+        std::fs::write(&verifying_key_file, verifier.to_pem().unwrap()).unwrap();
+        let signature_file = signer.sign_file(&path).unwrap();
+        assert!(Command::new("cosign")
+            .arg("verify-blob")
+            .arg("--insecure-ignore-tlog=true")
+            .arg("--key")
+            .arg(&verifying_key_file)
+            .arg("--signature")
+            .arg(&signature_file)
+            .arg(&path)
+            .status()
+            .unwrap()
+            .success());
+    }
+}
@@ -1,8 +1,12 @@
+#[cfg(feature = "cosign")]
+mod cosign;
 mod pgp;
 mod read;
 mod signer;
 mod write;
 
+#[cfg(feature = "cosign")]
+pub use self::cosign::*;
 pub use self::pgp::*;
 pub use self::read::*;
 pub use self::signer::*;
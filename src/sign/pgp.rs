@@ -1,3 +1,5 @@
+use std::io::Cursor;
+use std::io::ErrorKind;
 use std::io::Write;
 use std::time::SystemTime;
 
@@ -7,6 +9,7 @@ use pgp::crypto::{hash::HashAlgorithm, public_key::PublicKeyAlgorithm};
 use pgp::packet::*;
 use pgp::types::public::PublicParams;
 use pgp::types::PublicKeyTrait;
+use pgp::Deserializable;
 use pgp::SignedPublicKey;
 use pgp::SignedSecretKey;
 use rand::rngs::OsRng;
@@ -181,6 +184,41 @@ impl PgpCleartextVerifier {
     }
 }
 
+/// Read OpenPGP secret key either in ASCII-armored or in binary format.
+pub fn read_signing_key(bytes: &[u8]) -> Result<SignedSecretKey, std::io::Error> {
+    read_key(bytes, "secret key")
+}
+
+/// Read OpenPGP public key either in ASCII-armored or in binary format.
+pub fn read_verifying_key(bytes: &[u8]) -> Result<SignedPublicKey, std::io::Error> {
+    read_key(bytes, "public key")
+}
+
+fn read_key<K: Deserializable>(bytes: &[u8], what: &str) -> Result<K, std::io::Error> {
+    let (format, result) = if is_armored(bytes) {
+        (
+            "ASCII-armored",
+            K::from_armor_single(Cursor::new(bytes)).map(|(key, _headers)| key),
+        )
+    } else {
+        ("binary", K::from_bytes(Cursor::new(bytes)))
+    };
+    result.map_err(|e| {
+        std::io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "failed to read {} OpenPGP {} \
+                 (expected ASCII-armored or binary OpenPGP {}): {}",
+                format, what, what, e
+            ),
+        )
+    })
+}
+
+fn is_armored(bytes: &[u8]) -> bool {
+    bytes.trim_ascii_start().starts_with(b"-----BEGIN PGP ")
+}
+
 fn get_public_key_algorithm<P: PublicKeyTrait>(
     public_key: &P,
 ) -> Result<PublicKeyAlgorithm, Error> {
@@ -202,6 +240,7 @@ fn get_public_key_algorithm<P: PublicKeyTrait>(
 #[cfg(test)]
 mod tests {
     use pgp::composed::*;
+    use pgp::ser::Serialize;
 
     use super::*;
     use crate::test::pgp_keys;
@@ -218,6 +257,41 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn read_armored_and_binary_keys() {
+        let (signing_key, verifying_key) = pgp_keys(KeyType::Ed25519);
+        let armored = signing_key.to_armored_bytes(Default::default()).unwrap();
+        let binary = signing_key.to_bytes().unwrap();
+        for bytes in [armored, binary] {
+            let actual = read_signing_key(&bytes).unwrap();
+            assert_eq!(signing_key.fingerprint(), actual.fingerprint());
+        }
+        let armored = verifying_key.to_armored_bytes(Default::default()).unwrap();
+        let binary = verifying_key.to_bytes().unwrap();
+        for bytes in [armored, binary] {
+            let actual = read_verifying_key(&bytes).unwrap();
+            assert_eq!(verifying_key.fingerprint(), actual.fingerprint());
+        }
+        let error = read_verifying_key(b"-----BEGIN PGP PUBLIC KEY BLOCK-----\n").unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, error.kind());
+        assert!(
+            error.to_string().starts_with(
+                "failed to read ASCII-armored OpenPGP public key \
+                 (expected ASCII-armored or binary OpenPGP public key)"
+            ),
+            "error = {}",
+            error
+        );
+        let error = read_signing_key(b"not a key").unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("failed to read binary OpenPGP secret key"),
+            "error = {}",
+            error
+        );
+    }
+
     #[test]
     fn cleartext_sign_verify() {
         //let body = std::fs::read("InRelease.tmp").unwrap();
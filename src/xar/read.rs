@@ -8,6 +8,7 @@ use std::io::Error;
 use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
+use std::io::Take;
 use std::io::Write;
 use std::iter::FusedIterator;
 use std::path::Path;
@@ -46,8 +47,8 @@ pub struct XarArchive<R: Read + Seek> {
 impl<R: Read + Seek> XarArchive<R> {
     pub fn new(mut reader: R) -> Result<Self, Error> {
         let header = Header::read(&mut reader)?;
-        eprintln!("header {:?}", header);
-        eprintln!("header len {:?}", HEADER_LEN);
+        log::debug!("header {:?}", header);
+        log::debug!("header len {:?}", HEADER_LEN);
         let mut toc_bytes = vec![0_u8; header.toc_len_compressed as usize];
         reader.read_exact(&mut toc_bytes[..])?;
         let toc = xml::Xar::read(&toc_bytes[..])?.toc;
@@ -246,8 +247,9 @@ pub struct Entry<'a, R: Read + Seek> {
 }
 
 impl<'a, R: Read + Seek> Entry<'a, R> {
-    pub fn reader(&mut self) -> Result<XarDecoder<&mut R>, Error> {
+    pub fn reader(&mut self) -> Result<XarDecoder<Take<&mut R>>, Error> {
         self.archive.seek_to_file(self.i)?;
+        let length = self.archive.files[self.i].data.length;
         // we need decoder based on compression, otherwise we can accidentally decompress the
         // file with octet-stream compression
         let compression: XarCompression = self.archive.files[self.i]
@@ -256,7 +258,7 @@ impl<'a, R: Read + Seek> Entry<'a, R> {
             .style
             .as_str()
             .into();
-        Ok(compression.decoder(self.archive.reader.by_ref()))
+        Ok(compression.decoder(self.archive.reader.by_ref().take(length)))
     }
 
     pub fn file(&self) -> &xml::File {
@@ -793,7 +795,7 @@ pub mod xml {
                 toc_len_uncompressed: toc_len_uncompressed as u64,
                 checksum_algo,
             };
-            eprintln!("write header {:?}", header);
+            log::debug!("write header {:?}", header);
             header.write(writer.by_ref())?;
             writer.write_all(&toc_compressed)?;
             let checksum = Checksum::new_from_data(checksum_algo, &toc_compressed);